@@ -3,6 +3,7 @@ use backing_store::{BackingCacheStats, BackingPtr};
 use repr::bdd::*;
 use repr::var_label::VarLabel;
 use manager::var_order::VarOrder;
+use std::collections::HashMap;
 
 const DEFAULT_SUBTABLE_SZ: usize = 16384;
 
@@ -11,6 +12,10 @@ const DEFAULT_SUBTABLE_SZ: usize = 16384;
 pub struct BddTable {
     subtables: Vec<BackedRobinHoodTable<ToplessBdd>>,
     order: VarOrder,
+    /// maps the (regular) pointer of each node rebuilt by `swap` to the node
+    /// which now represents its function; the slots of the rebuilt nodes are
+    /// kept until `release_forwarded`
+    forward: HashMap<BddPtr, BddPtr>,
 }
 
 
@@ -24,6 +29,7 @@ impl BddTable {
         BddTable {
             subtables: v,
             order: order,
+            forward: HashMap::new(),
         }
     }
 
@@ -53,6 +59,103 @@ impl BddTable {
         }
     }
 
+    /// Fetch a reduced node with the given children, normalizing the
+    /// complemented edges so that the high edge is never complemented
+    fn mk_node(&mut self, low: BddPtr, high: BddPtr, var: VarLabel) -> BddPtr {
+        if low == high {
+            low
+        } else if high.is_compl() {
+            self.get_or_insert(Bdd::new_node(low.neg(), high.neg(), var)).neg()
+        } else {
+            self.get_or_insert(Bdd::new_node(low, high, var))
+        }
+    }
+
+    /// Returns the pair `(ptr | lbl = F, ptr | lbl = T)` for a `ptr` whose top
+    /// variable is at or below `lbl`
+    fn top_cofactors(&self, ptr: BddPtr, lbl: VarLabel) -> (BddPtr, BddPtr) {
+        if ptr.is_const() || ptr.label() != lbl {
+            (ptr, ptr)
+        } else {
+            let n = self.deref(ptr).into_node();
+            if ptr.is_compl() {
+                (n.low.neg(), n.high.neg())
+            } else {
+                (n.low, n.high)
+            }
+        }
+    }
+
+    /// Swaps the variables at positions `level` and `level + 1` of the order
+    /// while preserving the function of every node. Nodes of the upper
+    /// variable `x` which depend on the lower variable `y` are rebuilt as
+    /// `y`-nodes, and their parents are rewritten in place to point to the
+    /// rebuilt nodes. The old nodes are detached from the table but keep their
+    /// slots, and `resolve` forwards their pointers to the replacements.
+    /// Returns a map from the (regular) pointers of the rewritten `x`-nodes to
+    /// their replacements.
+    pub fn swap(&mut self, level: usize) -> HashMap<BddPtr, BddPtr> {
+        let x = self.order.var_at_pos(level);
+        let y = self.order.var_at_pos(level + 1);
+        let mut remap = HashMap::new();
+        for p in self.subtables[x.value() as usize].live_ptrs() {
+            let n = self.subtables[x.value() as usize].deref(p);
+            let dep_low = !n.low.is_const() && n.low.label() == y;
+            let dep_high = !n.high.is_const() && n.high.label() == y;
+            if !dep_low && !dep_high {
+                // this node does not depend on y, so it is unaffected
+                continue;
+            }
+            let (f00, f01) = self.top_cofactors(n.low, y);
+            let (f10, f11) = self.top_cofactors(n.high, y);
+            let new_low = self.mk_node(f00, f10, x);
+            let new_high = self.mk_node(f01, f11, x);
+            let r = self.mk_node(new_low, new_high, y);
+            remap.insert(BddPtr::new(x, TableIndex::new(p.0 as u64)), r);
+        }
+        if !remap.is_empty() {
+            let f = |ptr: BddPtr| match remap.get(&ptr.regular()) {
+                Some(r) => if ptr.is_compl() { r.neg() } else { *r },
+                None => ptr,
+            };
+            for pos in 0..level {
+                let var = self.order.var_at_pos(pos);
+                self.subtables[var.value() as usize].update(&|n: &ToplessBdd| {
+                    let new_n = ToplessBdd::new(f(n.low), f(n.high));
+                    if new_n != *n { Some(new_n) } else { None }
+                });
+            }
+            for tgt in self.forward.values_mut() {
+                *tgt = f(*tgt);
+            }
+            let old: Vec<BackingPtr> = remap.keys().map(|p| BackingPtr(p.idx() as u32)).collect();
+            self.subtables[x.value() as usize].detach(&old);
+            self.forward.extend(remap.iter().map(|(k, v)| (*k, *v)));
+        }
+        self.order.swap(x, y);
+        remap
+    }
+
+    /// The live node which represents the function of `ptr`, following the
+    /// forwarding left by `swap` if the node of `ptr` has been rebuilt
+    pub fn resolve(&self, ptr: BddPtr) -> BddPtr {
+        if ptr.is_const() || self.forward.is_empty() {
+            return ptr;
+        }
+        match self.forward.get(&ptr.regular()) {
+            Some(r) => if ptr.is_compl() { r.neg() } else { *r },
+            None => ptr,
+        }
+    }
+
+    /// Gives back the slots of every node rebuilt by `swap`, so that they can
+    /// be reused; pointers to those nodes are no longer forwarded
+    pub fn release_forwarded(&mut self) {
+        for (p, _) in self.forward.drain() {
+            self.subtables[p.var() as usize].release(&[BackingPtr(p.idx() as u32)]);
+        }
+    }
+
     pub fn deref(&self, ptr: BddPtr) -> Bdd {
         match ptr.ptr_type() {
             PointerType::PtrFalse => Bdd::BddFalse,
@@ -65,6 +168,49 @@ impl BddTable {
         }
     }
 
+    /// Marks the node pointed to by `ptr` as reachable for the next call to
+    /// `sweep`. Returns true if the node was not already marked; constant
+    /// pointers are never marked.
+    pub fn mark(&mut self, ptr: BddPtr) -> bool {
+        if ptr.is_const() {
+            return false;
+        }
+        self.subtables[ptr.var() as usize].mark(BackingPtr(ptr.idx() as u32))
+    }
+
+    /// true if `ptr` is a constant or has been marked since the last sweep
+    pub fn is_marked(&self, ptr: BddPtr) -> bool {
+        if ptr.is_const() {
+            return true;
+        }
+        self.subtables[ptr.var() as usize].is_marked(BackingPtr(ptr.idx() as u32))
+    }
+
+    /// Reclaims all unmarked nodes from every subtable and clears the marks.
+    /// Returns the total number of reclaimed nodes.
+    pub fn sweep(&mut self) -> usize {
+        let mut cnt = 0;
+        for tbl in self.subtables.iter_mut() {
+            cnt += tbl.sweep();
+        }
+        cnt
+    }
+
+    /// Pointers to every node in the table
+    pub fn live_nodes(&self) -> Vec<BddPtr> {
+        let mut r = Vec::new();
+        for (var, tbl) in self.subtables.iter().enumerate() {
+            let lbl = VarLabel::new(var as u64);
+            r.extend(tbl.live_ptrs().iter().map(|p| BddPtr::new(lbl, TableIndex::new(p.0 as u64))));
+        }
+        r
+    }
+
+    /// The number of nodes allocated for the variable `lbl`
+    pub fn num_nodes_var(&self, lbl: VarLabel) -> usize {
+        self.subtables[lbl.value() as usize].num_nodes()
+    }
+
     pub fn num_nodes(&self) -> usize {
         let mut cnt = 0;
         for tbl in self.subtables.iter() {
@@ -46,7 +46,8 @@ where
 {
     elem: T,
     hash_mem: usize, // store the hash value so that it is not recomputed
-    mark: bool       // a mark used during garbage collection
+    mark: bool,      // a mark used during garbage collection
+    free: bool       // true if this slot has been reclaimed and may be reused
 }
 
 impl<T> BackingElem<T>
@@ -58,7 +59,7 @@ where
     }
 
     fn new(elem: T, hash: usize) -> BackingElem<T> {
-        BackingElem { elem: elem, hash_mem: hash, mark: false }
+        BackingElem { elem, hash_mem: hash, mark: false, free: false }
     }
 }
 
@@ -99,6 +100,8 @@ where
     tbl: Vec<HashTableElement>,
     /// backing store for BDDs
     elem: Vec<BackingElem<T>>,
+    /// indices of reclaimed slots in `elem` which can be reused on insertion
+    free: Vec<u32>,
     cap: usize,
     /// the length of `tbl`
     len: usize,
@@ -114,6 +117,7 @@ where
         let v: Vec<HashTableElement> = zero_vec(sz);
         let r = BackedRobinHoodTable {
             elem: Vec::with_capacity(sz as usize),
+            free: Vec::new(),
             tbl: v,
             cap: sz,
             len: 0,
@@ -144,6 +148,23 @@ where
         propagate(&mut self.tbl, self.cap, itm, pos)
     }
 
+    /// The index that the next freshly inserted element will occupy; reclaimed
+    /// slots are reused before the backing store is extended
+    fn next_idx(&self) -> u32 {
+        match self.free.last() {
+            Some(&idx) => idx,
+            None => self.elem.len() as u32,
+        }
+    }
+
+    /// Place `e` into the slot given by `next_idx`
+    fn alloc(&mut self, e: BackingElem<T>) {
+        match self.free.pop() {
+            Some(idx) => self.elem[idx as usize] = e,
+            None => self.elem.push(e),
+        }
+    }
+
     /// Get or insert a fresh (low, high) pair
     pub fn get_or_insert(&mut self, elem: &T) -> BackingPtr {
         if (self.len + 1) as f64 > (self.cap as f64 * LOAD_FACTOR) {
@@ -155,7 +176,7 @@ where
         let hash_v = hasher.finish() as usize;
         let mut pos = hash_v % self.cap;
         let mut searcher =
-            HashTableElement::new(BackingPtr(self.elem.len() as u32), hash_v as u64);
+            HashTableElement::new(BackingPtr(self.next_idx()), hash_v as u64);
         loop {
             if self.is_occupied(pos) {
                 let cur_itm = self.tbl[pos].clone();
@@ -172,7 +193,7 @@ where
                 // check if this item's position is closer than ours
                 if cur_itm.offset() < searcher.offset() {
                     // insert the fresh item here
-                    self.elem.push(BackingElem::new(elem.clone(), hash_v));
+                    self.alloc(BackingElem::new(elem.clone(), hash_v));
                     self.tbl[pos] = searcher;
                     self.len += 1;
                     // propagate the element we swapped for
@@ -184,7 +205,7 @@ where
                 pos = (pos + 1) % self.cap; // wrap to the beginning of the array
             } else {
                 // place the element in the current spot, we're done
-                self.elem.push(BackingElem::new(elem.clone(), hash_v));
+                self.alloc(BackingElem::new(elem.clone(), hash_v));
                 self.len += 1;
                 let idx = searcher.idx();
                 self.tbl[pos] = searcher;
@@ -229,16 +250,145 @@ where
     /// Expands the capacity of the hash table
     pub fn grow(&mut self) -> () {
         let new_sz = (self.cap + 1).next_power_of_two();
-        self.cap = new_sz;
-        self.tbl = zero_vec(new_sz);
+        self.rehash(new_sz);
+    }
+
+    /// Rebuilds the hash table with capacity `sz` from the live elements of
+    /// the backing store
+    fn rehash(&mut self, sz: usize) {
+        self.cap = sz;
+        self.tbl = zero_vec(sz);
         let c = self.cap;
         for (idx, i) in self.elem.iter().enumerate() {
+            if i.free {
+                continue;
+            }
             let hash_v = i.hash();
             let hashelem = HashTableElement::new(BackingPtr(idx as u32), hash_v as u64);
             propagate(&mut self.tbl, self.cap, hashelem, hash_v % c);
         }
     }
 
+    /// Marks the element at `ptr` as reachable for the next call to `sweep`.
+    /// Returns true if the element was not already marked.
+    pub fn mark(&mut self, ptr: BackingPtr) -> bool {
+        let e = &mut self.elem[ptr.0 as usize];
+        let fresh = !e.mark;
+        e.mark = true;
+        fresh
+    }
+
+    pub fn is_marked(&self, ptr: BackingPtr) -> bool {
+        self.elem[ptr.0 as usize].mark
+    }
+
+    /// Reclaims every element that was not marked since the last sweep and
+    /// clears all marks. Pointers to reclaimed elements are invalidated, and
+    /// their slots are reused by subsequent insertions. Returns the number of
+    /// reclaimed elements.
+    pub fn sweep(&mut self) -> usize {
+        let mut num_freed = 0;
+        for (idx, e) in self.elem.iter_mut().enumerate() {
+            if e.free {
+                continue;
+            }
+            if e.mark {
+                e.mark = false;
+            } else {
+                e.free = true;
+                self.free.push(idx as u32);
+                num_freed += 1;
+            }
+        }
+        if num_freed > 0 {
+            self.len -= num_freed;
+            let c = self.cap;
+            self.rehash(c);
+        }
+        num_freed
+    }
+
+    /// Reclaims the elements at `ptrs`, which must be live. As with `sweep`,
+    /// pointers to them are invalidated and their slots may be reused.
+    pub fn remove(&mut self, ptrs: &[BackingPtr]) {
+        for p in ptrs.iter() {
+            let e = &mut self.elem[p.0 as usize];
+            assert!(!e.free, "removing a reclaimed element");
+            e.free = true;
+            e.mark = false;
+            self.free.push(p.0);
+        }
+        if !ptrs.is_empty() {
+            self.len -= ptrs.len();
+            let c = self.cap;
+            self.rehash(c);
+        }
+    }
+
+    /// Removes the elements at `ptrs`, which must be live, from the table
+    /// without giving up their slots: they can still be dereferenced, but are
+    /// no longer found by lookups, and their slots are not reused until they
+    /// are given back with `release`.
+    pub fn detach(&mut self, ptrs: &[BackingPtr]) {
+        for p in ptrs.iter() {
+            let e = &mut self.elem[p.0 as usize];
+            assert!(!e.free, "detaching a reclaimed element");
+            e.free = true;
+            e.mark = false;
+        }
+        if !ptrs.is_empty() {
+            self.len -= ptrs.len();
+            let c = self.cap;
+            self.rehash(c);
+        }
+    }
+
+    /// Makes the slots of elements removed by `detach` available for reuse;
+    /// pointers to them are invalidated
+    pub fn release(&mut self, ptrs: &[BackingPtr]) {
+        self.free.extend(ptrs.iter().map(|p| p.0));
+    }
+
+    /// Pointers to every live element of the backing store
+    pub fn live_ptrs(&self) -> Vec<BackingPtr> {
+        self.elem
+            .iter()
+            .enumerate()
+            .filter(|&(_, e)| !e.free)
+            .map(|(idx, _)| BackingPtr(idx as u32))
+            .collect()
+    }
+
+    /// Replaces each live element `e` for which `f(e)` returns `Some` with the
+    /// new value, keeping its position in the backing store so that existing
+    /// pointers remain valid. The caller must guarantee that no two elements
+    /// become equal.
+    pub fn update<F>(&mut self, f: &F)
+    where
+        F: Fn(&T) -> Option<T>,
+    {
+        let mut changed = false;
+        for e in self.elem.iter_mut() {
+            if e.free {
+                continue;
+            }
+            match f(&e.elem) {
+                None => (),
+                Some(new_elem) => {
+                    let mut hasher = FnvHasher::default();
+                    new_elem.hash(&mut hasher);
+                    e.hash_mem = hasher.finish() as usize;
+                    e.elem = new_elem;
+                    changed = true;
+                }
+            }
+        }
+        if changed {
+            let c = self.cap;
+            self.rehash(c);
+        }
+    }
+
     pub fn average_offset(&self) -> f64 {
         let total = self.tbl.iter().fold(0, |sum, ref cur| cur.offset() + sum);
         (total as f64) / (self.len as f64)
@@ -272,3 +422,32 @@ fn rh_simple() {
     }
     println!("average offset: {}", store.average_offset());
 }
+
+#[test]
+fn rh_sweep() {
+    let mut store: BackedRobinHoodTable<ToplessBdd> = BackedRobinHoodTable::new(5000);
+    let mut ptrs = Vec::new();
+    for i in 0..10000 {
+        let e = ToplessBdd::new(mk_ptr(i), mk_ptr(i));
+        ptrs.push(store.get_or_insert(&e));
+    }
+    // keep only the even elements alive
+    for i in (0..10000).filter(|i| i % 2 == 0) {
+        store.mark(ptrs[i]);
+    }
+    assert_eq!(store.sweep(), 5000);
+    assert_eq!(store.num_nodes(), 5000);
+    for i in 0..10000 {
+        let e = ToplessBdd::new(mk_ptr(i as u64), mk_ptr(i as u64));
+        if i % 2 == 0 {
+            assert_eq!(store.find(e), Some(ptrs[i]));
+        } else {
+            assert_eq!(store.find(e), None);
+        }
+    }
+    // reinserting reuses the reclaimed slots
+    let e = ToplessBdd::new(mk_ptr(1), mk_ptr(1));
+    let p = store.get_or_insert(&e);
+    assert_eq!(store.deref(p), &e);
+    assert_eq!(store.num_nodes(), 5001);
+}
@@ -16,7 +16,7 @@ const DEFAULT_RH_SZ: usize = 32768;
 /// allocators, and SDD allocators
 enum SubTable {
     BddSubTable{
-        man: BddManager,
+        man: Box<BddManager>,
         /// convert from BDD var labels to SDD var labels
         conv: HashMap<VarLabel, VarLabel>,
     },
@@ -36,6 +36,26 @@ pub struct SddTable {
 
 impl SddTable {
     pub fn new(vtree: &VTree) -> SddTable {
+        SddTable::build(vtree, BddManager::new)
+    }
+
+    /// Builds a table for `vtree` which takes over the BDD managers of `old`,
+    /// where `leaf_map` maps each leaf of `old` to the leaf of `vtree` with
+    /// the same variables. The or-nodes of `old` are left in place.
+    pub fn with_bdd_mans(vtree: &VTree, old: &mut SddTable,
+                         leaf_map: &HashMap<usize, usize>) -> SddTable {
+        let mut t = SddTable::build(vtree, |_| BddManager::new(VarOrder::new(Vec::new())));
+        for (&old_idx, &new_idx) in leaf_map.iter() {
+            let man = ::std::mem::replace(old.bdd_man_mut(old_idx),
+                                          BddManager::new(VarOrder::new(Vec::new())));
+            *t.bdd_man_mut(new_idx) = man;
+        }
+        t
+    }
+
+    /// Builds a table for `vtree`, where `mk_man` creates the BDD manager
+    /// of each leaf from its variable order
+    fn build<F: FnMut(VarOrder) -> BddManager>(vtree: &VTree, mut mk_man: F) -> SddTable {
         let mut t = SddTable {
             tables: Vec::new(),
             sdd_to_bdd: HashMap::new(),
@@ -54,8 +74,8 @@ impl SddTable {
                         m.insert(VarLabel::new(var_idx as u64), v.clone());
                         new_order.push(VarLabel::new(var_idx as u64));
                     }
-                    let man = BddManager::new(VarOrder::new(new_order));
-                    t.tables.push(SubTable::BddSubTable { man: man, conv: m })
+                    let man = mk_man(VarOrder::new(new_order));
+                    t.tables.push(SubTable::BddSubTable { man: Box::new(man), conv: m })
                 },
                 &BTree::Node(_, _, _) => {
                     let s = SubTable::SddSubTable {
@@ -95,6 +115,95 @@ impl SddTable {
     }
 
 
+    /// The regular pointers to every or-node at the vtree node `vnode`
+    pub fn live_sdds(&self, vnode: usize) -> Vec<SddPtr> {
+        match self.tables[vnode] {
+            SubTable::SddSubTable{ref tbl} => {
+                tbl.live_ptrs().iter().map(|p| SddPtr::new_node(p.0 as usize, vnode as u16)).collect()
+            },
+            _ => Vec::new()
+        }
+    }
+
+    /// Reclaims the or-nodes `ptrs`, which must all live at the vtree node
+    /// `vnode`
+    pub fn remove_sdds(&mut self, vnode: usize, ptrs: &[SddPtr]) {
+        match self.tables[vnode] {
+            SubTable::SddSubTable{ref mut tbl} => {
+                let ptrs: Vec<BackingPtr> = ptrs.iter().map(|p| BackingPtr(p.idx() as u32)).collect();
+                tbl.remove(&ptrs)
+            },
+            _ => panic!("removing SDD nodes from a BDD")
+        }
+    }
+
+    /// Rewrites every prime and sub of the or-nodes at `vnode` with `f`,
+    /// keeping each node in place. `f` must be injective and preserve the
+    /// order of the primes of each node, so that the nodes stay canonical.
+    pub fn relabel_sdds<F: Fn(SddPtr) -> SddPtr>(&mut self, vnode: usize, f: &F) {
+        if let SubTable::SddSubTable{ref mut tbl} = self.tables[vnode] {
+            tbl.update(&|elems: &Vec<(SddPtr, SddPtr)>| {
+                Some(elems.iter().map(|&(p, s)| (f(p), f(s))).collect())
+            })
+        }
+    }
+
+    /// Moves the nodes of each vtree node `i` to the vtree node `perm[i]`.
+    /// The pointers held by the or-nodes are left unchanged.
+    pub fn permute(&mut self, perm: &[usize]) {
+        let old = ::std::mem::take(&mut self.tables);
+        let mut slots: Vec<Option<SubTable>> = old.into_iter().map(Some).collect();
+        let mut inv = vec![0; perm.len()];
+        for (i, &p) in perm.iter().enumerate() {
+            inv[p] = i;
+        }
+        for &i in inv.iter() {
+            self.tables.push(slots[i].take().unwrap());
+        }
+    }
+
+    /// Marks the or-node `ptr` as reachable for the next call to `sweep_sdd`.
+    /// Returns true if the node was not already marked.
+    pub fn mark_sdd(&mut self, ptr: SddPtr) -> bool {
+        match self.tables[ptr.vtree()] {
+            SubTable::SddSubTable{ref mut tbl} => tbl.mark(BackingPtr(ptr.idx() as u32)),
+            _ => panic!("marking BDD as an SDD")
+        }
+    }
+
+    /// true if the or-node `ptr` has been marked since the last sweep
+    pub fn is_marked_sdd(&self, ptr: SddPtr) -> bool {
+        match self.tables[ptr.vtree()] {
+            SubTable::SddSubTable{ref tbl} => tbl.is_marked(BackingPtr(ptr.idx() as u32)),
+            _ => panic!("checking BDD mark as an SDD")
+        }
+    }
+
+    /// Reclaims every unmarked or-node and clears the marks. Returns the number
+    /// of reclaimed nodes. The sub-BDD managers are not affected.
+    pub fn sweep_sdd(&mut self) -> usize {
+        let mut cnt = 0;
+        for t in self.tables.iter_mut() {
+            if let SubTable::SddSubTable{ref mut tbl} = *t {
+                cnt += tbl.sweep();
+            }
+        }
+        cnt
+    }
+
+    /// Total number of nodes allocated, counting both or-nodes and the nodes
+    /// of every sub-BDD manager
+    pub fn num_nodes(&self) -> usize {
+        let mut cnt = 0;
+        for t in self.tables.iter() {
+            match *t {
+                SubTable::SddSubTable{ref tbl} => cnt += tbl.num_nodes(),
+                SubTable::BddSubTable{ref man, conv: _} => cnt += man.num_nodes(),
+            }
+        }
+        cnt
+    }
+
     /// Fetch the iterator for a particular SDD or-node.
     ///
     /// Note: This invokes unsafe behavior to decouple the lifetime of `&self`
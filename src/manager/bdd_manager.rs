@@ -3,17 +3,87 @@
 use manager::cache::bdd_app::*;
 use std::fmt::Debug;
 use manager::var_order::VarOrder;
-use repr::var_label::VarLabel;
+use repr::var_label::{VarLabel, Literal};
 use repr::bdd::*;
+use repr::bool_op::{BoolOp, Unary};
+use repr::semiring::*;
 use repr::cnf::Cnf;
 use repr::boolexpr::BoolExpr;
 use std::collections::{HashMap, HashSet};
 use backing_store::BackingCacheStats;
 use backing_store::bdd_table::BddTable;
+use manager::ref_table::{ExternalRef, ExternalRefTable};
+use manager::model_iter::ModelIter;
 use num::traits::Num;
+use num::{BigUint, One, Zero};
+use rand::Rng;
+use std::cmp;
+use std::mem;
 #[macro_use]
 use maplit::*;
 
+/// The maximum factor by which the BDD is allowed to grow while sifting a
+/// single variable before the search in that direction is abandoned
+const MAX_SIFT_GROWTH: f64 = 1.2;
+
+
+/// Applies the result of fixing one argument of an operator to `f`
+fn apply_unary(u: Unary, f: BddPtr) -> BddPtr {
+    match u {
+        Unary::Const(true) => BddPtr::true_node(),
+        Unary::Const(false) => BddPtr::false_node(),
+        Unary::Id => f,
+        Unary::Neg => f.neg(),
+    }
+}
+
+/// A lazy iterator over the paths to true in a BDD. Each path is a cube
+/// (partial assignment) and the cubes are pairwise disjoint.
+pub struct BddCubeIter<'a> {
+    man: &'a BddManager,
+    /// the nodes which remain to be explored, along with the cube which leads
+    /// to each
+    stack: Vec<(BddPtr, Vec<Literal>)>,
+}
+
+impl<'a> Iterator for BddCubeIter<'a> {
+    type Item = Vec<Literal>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((ptr, cube)) = self.stack.pop() {
+            if ptr.is_true() {
+                return Some(cube);
+            } else if ptr.is_false() {
+                continue;
+            }
+            let lbl = ptr.label();
+            let (low, high) = self.man.top_cofactors(ptr, lbl);
+            let mut low_cube = cube.clone();
+            low_cube.push(Literal::new(lbl, false));
+            let mut high_cube = cube;
+            high_cube.push(Literal::new(lbl, true));
+            self.stack.push((low, low_cube));
+            self.stack.push((high, high_cube));
+        }
+        None
+    }
+}
+
+/// the larger of `a` and `b`
+fn max_weight<T: PartialOrd + Copy>(a: T, b: T) -> T {
+    if a >= b { a } else { b }
+}
+
+/// The MAP weight of true over the levels in `[a, b)`, where the first
+/// `num_query` levels are maximized and the rest are summed
+fn map_smooth<T: Num + Copy + PartialOrd>(weights: &[(T, T)], num_query: usize,
+                                          a: usize, b: usize) -> T {
+    let mut r = T::one();
+    for (pos, &(l, h)) in weights.iter().enumerate().take(b).skip(a) {
+        r = r * if pos < num_query { max_weight(l, h) } else { l + h };
+    }
+    r
+}
 
 /// Weighted model counting parameters for a BDD. It primarily is a storage for
 /// the weight on each variable.
@@ -50,11 +120,71 @@ impl<T: Num + Clone + Debug + Copy> BddWmc<T> {
     pub fn set_weight(&mut self, idx: VarLabel, low: T, high: T) -> () {
         self.var_to_val.insert(idx, (low, high));
     }
+
+    /// The weight of every variable as a pair of elements of the real
+    /// semiring, as taken by `wmc_semiring`
+    pub fn semiring_weights(&self) -> HashMap<VarLabel, (RealSemiring<T>, RealSemiring<T>)> {
+        self.var_to_val
+            .iter()
+            .map(|(k, &(low, high))| (*k, (RealSemiring(low), RealSemiring(high))))
+            .collect()
+    }
+}
+
+/// The weighted model count of every node reachable from a set of roots,
+/// precomputed once so that each sample is drawn in a single top-down pass
+pub struct BddSampleTable {
+    /// the `(low, high)` weight of the variable at each level
+    weights: Vec<(f64, f64)>,
+    /// the weighted model count of each node over the levels at or below it
+    counts: HashMap<BddPtr, f64>,
+}
+
+impl BddSampleTable {
+    fn count(&self, ptr: BddPtr) -> f64 {
+        if ptr.is_true() {
+            1.0
+        } else if ptr.is_false() {
+            0.0
+        } else {
+            self.counts[&ptr]
+        }
+    }
+}
+
+/// An endless iterator of independent samples from the models of a BDD, each
+/// drawn with probability proportional to its weight
+pub struct BddSampleIter<'a, R: Rng + 'a> {
+    man: &'a BddManager,
+    ptr: BddPtr,
+    table: BddSampleTable,
+    rng: &'a mut R,
+}
+
+impl<'a, R: Rng + 'a> Iterator for BddSampleIter<'a, R> {
+    type Item = HashMap<VarLabel, bool>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ptr.is_false() {
+            return None;
+        }
+        let lits = self.man.sample_draw(self.ptr, &self.table, self.rng);
+        Some(lits.iter().map(|l| (l.get_label(), l.get_polarity())).collect())
+    }
 }
 
 pub struct BddManager {
     compute_table: BddTable,
     apply_table: BddApplyTable,
+    /// cache for `ite`
+    ite_table: BddIteTable,
+    /// cache for `and_exists`
+    and_exists_table: BddAndExistsTable,
+    /// external references which serve as roots during garbage collection
+    ref_table: ExternalRefTable<BddPtr>,
+    /// node count above which `reorder_if_needed` triggers sifting; `None`
+    /// disables automatic reordering
+    reorder_threshold: Option<usize>,
 }
 
 impl BddManager {
@@ -69,6 +199,10 @@ impl BddManager {
         BddManager {
             compute_table: BddTable::new(order),
             apply_table: BddApplyTable::new(len),
+            ite_table: BddIteTable::new(len),
+            and_exists_table: BddAndExistsTable::new(len),
+            ref_table: ExternalRefTable::new(),
+            reorder_threshold: None,
         }
     }
 
@@ -77,6 +211,8 @@ impl BddManager {
     /// new variable
     pub fn new_var(&mut self) -> VarLabel {
         self.apply_table.new_last();
+        self.ite_table.new_last();
+        self.and_exists_table.new_last();
         self.compute_table.new_last()
     }
 
@@ -136,12 +272,212 @@ impl BddManager {
         }
     }
 
+    /// Generates an external reference to `ptr`, or increments the counter of
+    /// the existing one. Only BDDs which are reachable from an external
+    /// reference with a positive count survive `garbage_collect`.
+    pub fn ext_ref(&mut self, ptr: BddPtr) -> ExternalRef {
+        let ptr = self.resolve(ptr);
+        self.ref_table.gen_or_inc(ptr)
+    }
+
+    pub fn incref(&mut self, r: ExternalRef) {
+        self.ref_table.incref(r)
+    }
+
+    /// Decrement the counter of `r`; once it reaches zero the referenced BDD
+    /// may be reclaimed by the next collection
+    pub fn decref(&mut self, r: ExternalRef) {
+        self.ref_table.decref(r)
+    }
+
+    /// Fetch the BDD pointed to by the external reference `r`
+    pub fn get_ext(&self, r: ExternalRef) -> BddPtr {
+        self.ref_table.into_internal(r)
+    }
+
+    /// Mark-and-sweep garbage collection. Reclaims every node which is not
+    /// reachable from a live external reference, and drops all cached
+    /// applications which mention a reclaimed node. Any `BddPtr` which is not
+    /// protected by an external reference is invalid after this call. Returns
+    /// the number of reclaimed nodes.
+    pub fn garbage_collect(&mut self) -> usize {
+        self.compute_table.release_forwarded();
+        self.ref_table.remove_dead();
+        let roots = self.ref_table.roots();
+        self.mark(&roots);
+        self.sweep()
+    }
+
+    /// Marks every node reachable from `roots` as live for the next `sweep`
+    pub fn mark(&mut self, roots: &[BddPtr]) {
+        let mut stack: Vec<BddPtr> = roots.iter().map(|r| self.resolve(*r)).collect();
+        while let Some(ptr) = stack.pop() {
+            if self.compute_table.mark(ptr) {
+                let n = self.deref(ptr).into_node();
+                stack.push(n.low);
+                stack.push(n.high);
+            }
+        }
+    }
+
+    /// true if `ptr` has been marked live since the last sweep
+    pub fn is_marked(&self, ptr: BddPtr) -> bool {
+        let ptr = self.resolve(ptr);
+        self.compute_table.is_marked(ptr)
+    }
+
+    /// Reclaims every node which was not marked by `mark` and invalidates
+    /// the cached applications which mention them. Returns the number of
+    /// reclaimed nodes.
+    pub fn sweep(&mut self) -> usize {
+        {
+            let tbl = &self.compute_table;
+            self.apply_table.purge(&|p| tbl.is_marked(p));
+            self.ite_table.purge(&|p| tbl.is_marked(p));
+            self.and_exists_table.purge(&|p| tbl.is_marked(p));
+        }
+        self.compute_table.sweep()
+    }
+
+    /// The node which currently represents the function of `ptr`. Reordering
+    /// rebuilds some nodes under new pointers; the old pointers stay valid
+    /// and are forwarded to the rebuilt nodes until the next
+    /// `garbage_collect`.
+    fn resolve(&self, ptr: BddPtr) -> BddPtr {
+        self.compute_table.resolve(ptr)
+    }
+
+    /// Invalidates every cached application
+    fn flush_caches(&mut self) {
+        self.apply_table.clear();
+        self.ite_table.clear();
+        self.and_exists_table.clear();
+    }
+
+    /// Swaps the variables at positions `level` and `level + 1` in the order.
+    /// Every `BddPtr` keeps its function, and external references are updated
+    /// to point to the rewritten BDDs. No node is reclaimed; the nodes which
+    /// become unreachable are left for the next `garbage_collect`. The apply
+    /// caches are flushed.
+    pub fn swap_levels(&mut self, level: usize) {
+        self.flush_caches();
+        self.swap_levels_flushed(level);
+    }
+
+    /// Performs `swap_levels` assuming that the apply caches have already been
+    /// flushed, so that no cached application can mention a rewritten node
+    fn swap_levels_flushed(&mut self, level: usize) {
+        let remap = self.compute_table.swap(level);
+        self.ref_table.remap(&|p: &BddPtr| match remap.get(&p.regular()) {
+            Some(r) => if p.is_compl() { r.neg() } else { *r },
+            None => *p,
+        });
+    }
+
+    /// The number of nodes reachable from a live external reference
+    fn ref_size(&self) -> usize {
+        self.num_reachable(&self.ref_table.roots())
+    }
+
+    /// Moves the variable `lbl` through every position in the order and
+    /// leaves it at the position which minimizes `ref_size`. Assumes the
+    /// apply caches have been flushed.
+    fn sift_var(&mut self, lbl: VarLabel) {
+        let n = self.get_order().len();
+        let mut pos = self.get_order().get(lbl);
+        let mut best_size = self.ref_size();
+        let mut best_pos = pos;
+        let limit = (best_size as f64 * MAX_SIFT_GROWTH) as usize;
+        // sift down
+        while pos + 1 < n {
+            self.swap_levels_flushed(pos);
+            pos += 1;
+            let sz = self.ref_size();
+            if sz < best_size {
+                best_size = sz;
+                best_pos = pos;
+            } else if sz > limit {
+                break;
+            }
+        }
+        // sift up
+        while pos > 0 {
+            self.swap_levels_flushed(pos - 1);
+            pos -= 1;
+            let sz = self.ref_size();
+            if sz < best_size {
+                best_size = sz;
+                best_pos = pos;
+            } else if sz > limit && pos < best_pos {
+                break;
+            }
+        }
+        // return to the best position found
+        while pos < best_pos {
+            self.swap_levels_flushed(pos);
+            pos += 1;
+        }
+        while pos > best_pos {
+            self.swap_levels_flushed(pos - 1);
+            pos -= 1;
+        }
+    }
+
+    /// Rudell's sifting: each variable, in decreasing order of the number of
+    /// nodes labeled by it, is moved to the position in the order which
+    /// minimizes the number of nodes reachable from the external references.
+    ///
+    /// Every node which exists when sifting starts is kept, so every `BddPtr`
+    /// remains a valid pointer to the same function afterwards, whether or
+    /// not it is held by an external reference; the nodes which are not
+    /// referenced are left for the next `garbage_collect`. The nodes which are
+    /// built while sifting and become unreachable are reclaimed at the end.
+    pub fn reorder_sift(&mut self) {
+        self.flush_caches();
+        let keep = self.compute_table.live_nodes();
+        let mut vars: Vec<VarLabel> = (0..self.get_order().len())
+            .map(|v| VarLabel::new(v as u64))
+            .collect();
+        {
+            let tbl = &self.compute_table;
+            vars.sort_by_key(|v| cmp::Reverse(tbl.num_nodes_var(*v)));
+        }
+        for v in vars {
+            self.sift_var(v);
+        }
+        self.mark(&keep);
+        self.sweep();
+    }
+
+    /// Enables automatic reordering: once the number of allocated nodes
+    /// exceeds `threshold`, the next call to `reorder_if_needed` sifts the
+    /// order. `None`, the default, disables automatic reordering.
+    pub fn set_reorder_threshold(&mut self, threshold: Option<usize>) {
+        self.reorder_threshold = threshold;
+    }
+
+    /// Runs `reorder_sift` if automatic reordering is enabled and the node
+    /// count has crossed the threshold, after which the threshold is raised to
+    /// twice the reordered size. As with `reorder_sift`, every `BddPtr`
+    /// remains valid. Returns true if reordering took place.
+    pub fn reorder_if_needed(&mut self) -> bool {
+        match self.reorder_threshold {
+            Some(t) if self.num_nodes() > t => {
+                self.reorder_sift();
+                self.reorder_threshold = Some(cmp::max(t, 2 * self.num_nodes()));
+                true
+            }
+            _ => false,
+        }
+    }
+
     pub fn print_bdd(&self, ptr: BddPtr) -> String {
+        let ptr = self.resolve(ptr);
         use repr::bdd::PointerType::*;
         fn print_bdd_helper(t: &BddManager, ptr: BddPtr) -> String {
             match ptr.ptr_type() {
                 PtrTrue => String::from("T"),
-                PtrFalse => String::from("T"),
+                PtrFalse => String::from("F"),
                 PtrNode => {
                     let l_p = t.low(ptr);
                     let h_p = t.high(ptr);
@@ -150,24 +486,76 @@ impl BddManager {
                     format!(
                         "({}, {}{}, {}{})",
                         ptr.var(),
-                        if l_p.is_compl() { "!" } else { "" },
+                        if l_p.is_compl() && !l_p.is_const() { "!" } else { "" },
                         l_s,
-                        if h_p.is_compl() { "!" } else { "" },
+                        if h_p.is_compl() && !h_p.is_const() { "!" } else { "" },
                         r_s
                     )
                 }
             }
         }
         let s = print_bdd_helper(self, ptr);
-        format!("{}{}", if ptr.is_compl() { "!" } else { "" }, s)
+        format!("{}{}", if ptr.is_compl() && !ptr.is_const() { "!" } else { "" }, s)
     }
 
 
+    /// Renders `ptr` in the Graphviz DOT language. Each node is labeled with
+    /// its variable and the nodes of a variable share a rank, following the
+    /// variable order. High edges are solid and low edges are dashed. A
+    /// complemented edge (to the root or a low child) is red and ends in a
+    /// dot; there is a single terminal, `T`, whose complement is false.
+    pub fn to_dot(&self, ptr: BddPtr) -> String {
+        let ptr = self.resolve(ptr);
+        let id = |p: BddPtr| -> String {
+            if p.is_const() { String::from("t") } else { format!("n{}", p.regular().raw()) }
+        };
+        let mut r = String::from("digraph BDD {\n");
+        r.push_str("  root [shape=none, label=\"\"];\n");
+        let compl = "color=red, arrowhead=dot";
+        r.push_str(&format!("  root -> {}{};\n", id(ptr),
+                            if ptr.is_compl() { format!(" [{}]", compl) } else { String::new() }));
+        // the regular form of every reachable node, since complements are
+        // drawn as edges
+        let mut nodes = Vec::new();
+        let mut seen = HashSet::new();
+        let mut stack = vec![ptr.regular()];
+        while let Some(n) = stack.pop() {
+            if n.is_const() || !seen.insert(n) {
+                continue;
+            }
+            stack.push(self.low(n).regular());
+            stack.push(self.high(n));
+            nodes.push(n);
+        }
+        for lvl in 0..self.get_order().len() {
+            let var = self.get_order().var_at_pos(lvl);
+            let at_lvl: Vec<&BddPtr> = nodes.iter().filter(|n| n.label() == var).collect();
+            if at_lvl.is_empty() {
+                continue;
+            }
+            r.push_str("  { rank=same;");
+            for n in at_lvl {
+                r.push_str(&format!(" {} [label=\"{}\"];", id(*n), var.value()));
+            }
+            r.push_str(" }\n");
+        }
+        r.push_str("  t [shape=box, label=\"T\"];\n");
+        for n in nodes.iter() {
+            let (low, high) = (self.low(*n), self.high(*n));
+            r.push_str(&format!("  {} -> {};\n", id(*n), id(high)));
+            let style = if low.is_compl() { format!("style=dashed, {}", compl) } else { String::from("style=dashed") };
+            r.push_str(&format!("  {} -> {} [{}];\n", id(*n), id(low), style));
+        }
+        r.push_str("}\n");
+        r
+    }
+
     pub fn negate(&mut self, ptr: BddPtr) -> BddPtr {
         ptr.neg()
     }
 
     pub fn print_bdd_lbl(&self, ptr: BddPtr, map: &HashMap<VarLabel, VarLabel>) -> String {
+        let ptr = self.resolve(ptr);
         use repr::bdd::PointerType::*;
         fn print_bdd_helper(
             t: &BddManager,
@@ -176,7 +564,7 @@ impl BddManager {
         ) -> String {
             match ptr.ptr_type() {
                 PtrTrue => String::from("T"),
-                PtrFalse => String::from("T"),
+                PtrFalse => String::from("F"),
                 PtrNode => {
                     let l_p = t.low(ptr);
                     let h_p = t.high(ptr);
@@ -185,16 +573,16 @@ impl BddManager {
                     format!(
                         "({:?}, {}{}, {}{})",
                         map.get(&ptr.label()).unwrap().value(),
-                        if l_p.is_compl() { "!" } else { "" },
+                        if l_p.is_compl() && !l_p.is_const() { "!" } else { "" },
                         l_s,
-                        if h_p.is_compl() { "!" } else { "" },
+                        if h_p.is_compl() && !h_p.is_const() { "!" } else { "" },
                         r_s
                     )
                 }
             }
         }
         let s = print_bdd_helper(self, ptr, map);
-        format!("{}{}", if ptr.is_compl() { "!" } else { "" }, s)
+        format!("{}{}", if ptr.is_compl() && !ptr.is_const() { "!" } else { "" }, s)
     }
 
 
@@ -202,6 +590,7 @@ impl BddManager {
     /// true if `a` represents a variable (both high and low are constant)
     #[inline]
     pub fn is_var(&self, ptr: BddPtr) -> bool {
+        let ptr = self.resolve(ptr);
         match ptr.ptr_type() {
             PointerType::PtrNode => {
                 let b = self.compute_table.deref(ptr).into_node();
@@ -212,103 +601,74 @@ impl BddManager {
     }
 
 
-    pub fn and(&mut self, f: BddPtr, g: BddPtr) -> BddPtr {
-        // base case
-        let reg_f = f.regular();
-        let reg_g = g.regular();
-        if reg_f == reg_g {
-            if f == g {
-                return f;
-            } else {
-                return BddPtr::false_node();
-            }
+    /// Applies the Boolean operator `op` to `f` and `g`
+    pub fn apply(&mut self, op: BoolOp, f: BddPtr, g: BddPtr) -> BddPtr {
+        let f = self.resolve(f);
+        let g = self.resolve(g);
+        // base cases
+        if !op.depends_on_second() {
+            return apply_unary(op.fix_second(false), f);
         }
-        if reg_f.is_true() {
-            if f.is_true() {
-                return g;
-            } else {
-                return f;
-            }
+        if !op.depends_on_first() {
+            return apply_unary(op.fix_first(false), g);
         }
-        if reg_g.is_true() {
-            if g.is_true() {
-                return f;
-            } else {
-                return g;
-            }
+        if f.is_const() {
+            return apply_unary(op.fix_first(f.is_true()), g);
+        }
+        if g.is_const() {
+            return apply_unary(op.fix_second(g.is_true()), f);
+        }
+        if f == g {
+            return apply_unary(op.diag(), f);
+        }
+        if f == g.neg() {
+            return apply_unary(op.anti_diag(), f);
         }
 
         // now, both of the nodes are not constant
-        // normalize the nodes to increase cache efficiency
-        let (f, g, reg_f, _) = if reg_f < reg_g {
-            (f, g, reg_f, reg_g)
-        } else {
-            (g, f, reg_g, reg_f)
-        };
+        // normalize the application to increase cache efficiency: complemented
+        // arguments are folded into the operator, the operator is made false
+        // on (F, F) by complementing the result, and the arguments are sorted
+        let (f, op) = if f.is_compl() { (f.neg(), op.neg_first()) } else { (f, op) };
+        let (g, op) = if g.is_compl() { (g.neg(), op.neg_second()) } else { (g, op) };
+        let (op, compl) = if op.eval(false, false) { (op.neg(), true) } else { (op, false) };
+        let (f, g, op) = if f < g { (f, g, op) } else { (g, f, op.swap()) };
         // check the cache
-        match self.apply_table.get(f, g) {
-            Some(v) => {
-                return v;
-            }
-            None => {}
-        };
-
-        // now we know that these are nodes, compute the cofactors
-        let topf = self.get_order().get(f.label());
-        let topg = self.get_order().get(g.label());
-        let index; // will hold the top variable
-        let mut fv;
-        let mut gv;
-        let mut fnv;
-        let mut gnv;
-        if topf <= topg {
-            index = f.label();
-            fv = self.high(reg_f);
-            fnv = self.low(reg_f);
-            if f.is_compl() {
-                fv = fv.neg();
-                fnv = fnv.neg();
-            }
-        } else {
-            index = g.label();
-            fv = f;
-            fnv = f;
+        if let Some(v) = self.apply_table.get(op, f, g) {
+            return if compl { v.neg() } else { v };
         }
 
-        if topg <= topf {
-            gv = self.high(g);
-            gnv = self.low(g);
-            if g.is_compl() {
-                gv = gv.neg();
-                gnv = gnv.neg();
-            }
+        // compute the cofactors with respect to the top variable
+        let index = if self.get_order().lte(f.label(), g.label()) {
+            f.label()
         } else {
-            gv = g;
-            gnv = g;
-        }
+            g.label()
+        };
+        let (fnv, fv) = self.top_cofactors(f, index);
+        let (gnv, gv) = self.top_cofactors(g, index);
 
         // now recurse
-        let new_h = self.and(fv, gv);
-        let new_l = self.and(fnv, gnv);
+        let new_h = self.apply(op, fv, gv);
+        let new_l = self.apply(op, fnv, gnv);
 
         // now normalize the result
-        if new_h == new_l {
-            return new_h;
+        let r = if new_h == new_l {
+            new_h
         } else {
-            let n = BddNode {
-                low: new_l,
-                high: new_h,
-                var: index,
-            };
-            let r = self.get_or_insert(n);
-            self.apply_table.insert(f, g, r);
-            return r;
-        }
+            self.get_or_insert(BddNode::new(new_l, new_h, index))
+        };
+        self.apply_table.insert(op, f, g, r);
+        if compl { r.neg() } else { r }
+    }
+
+    /// Compute the Boolean function `f /\ g`
+    pub fn and(&mut self, f: BddPtr, g: BddPtr) -> BddPtr {
+        self.apply(BoolOp::And, f, g)
     }
 
     /// Compute the Boolean function `f || g`
     pub fn or(&mut self, f: BddPtr, g: BddPtr) -> BddPtr {
-        self.and(f.neg(), g.neg()).neg()
+        self.apply(BoolOp::Or, f, g)
     }
 
     /// disjoins a list of BDDs
@@ -332,50 +692,262 @@ impl BddManager {
 
     /// Compute the Boolean function `f iff g`
     pub fn iff(&mut self, f: BddPtr, g: BddPtr) -> BddPtr {
-        // TODO: for now, compute this as (f => g) /\ (g => f); this can be
-        // improved later
-        let f_imp_g = self.or(f.neg(), g);
-        let g_imp_f = self.or(g.neg(), f);
-        self.and(f_imp_g, g_imp_f)
+        self.apply(BoolOp::Iff, f, g)
+    }
+
+    /// Compute the Boolean function `f xor g`
+    pub fn xor(&mut self, f: BddPtr, g: BddPtr) -> BddPtr {
+        self.apply(BoolOp::Xor, f, g)
+    }
+
+    /// Compute the Boolean function `f => g`; see `implies` for the
+    /// implication check, which builds no nodes
+    pub fn imp(&mut self, f: BddPtr, g: BddPtr) -> BddPtr {
+        self.apply(BoolOp::Implies, f, g)
+    }
+
+    /// Compute the Boolean function `!(f /\ g)`
+    pub fn nand(&mut self, f: BddPtr, g: BddPtr) -> BddPtr {
+        self.apply(BoolOp::Nand, f, g)
+    }
+
+    /// Compute the Boolean function `!(f \/ g)`
+    pub fn nor(&mut self, f: BddPtr, g: BddPtr) -> BddPtr {
+        self.apply(BoolOp::Nor, f, g)
+    }
+
+    /// true if `a` should be the condition of a standard triple in place of
+    /// `b`, i.e. if it occurs earlier in the order (ties are broken by the
+    /// pointer itself). Both must be non-constant.
+    fn ite_precedes(&self, a: BddPtr, b: BddPtr) -> bool {
+        let pos_a = self.get_order().get(a.label());
+        let pos_b = self.get_order().get(b.label());
+        pos_a < pos_b || (pos_a == pos_b && a.regular() < b.regular())
+    }
+
+    /// Compute the Boolean function `if f then g else h`
+    pub fn ite(&mut self, f: BddPtr, g: BddPtr, h: BddPtr) -> BddPtr {
+        let f = self.resolve(f);
+        let g = self.resolve(g);
+        let h = self.resolve(h);
+        // base cases
+        if f.is_true() {
+            return g;
+        }
+        if f.is_false() {
+            return h;
+        }
+        let mut g = if g == f {
+            BddPtr::true_node()
+        } else if g == f.neg() {
+            BddPtr::false_node()
+        } else {
+            g
+        };
+        let mut h = if h == f {
+            BddPtr::false_node()
+        } else if h == f.neg() {
+            BddPtr::true_node()
+        } else {
+            h
+        };
+        if g == h {
+            return g;
+        }
+        if g.is_true() && h.is_false() {
+            return f;
+        }
+        if g.is_false() && h.is_true() {
+            return f.neg();
+        }
+
+        // put the triple in standard form: among equivalent triples, the
+        // condition is the argument which comes first
+        let mut f = f;
+        if g.is_true() {
+            // ite(f, T, h) = ite(h, T, f)
+            if self.ite_precedes(h, f) {
+                mem::swap(&mut f, &mut h);
+            }
+        } else if g.is_false() {
+            // ite(f, F, h) = ite(!h, F, !f)
+            if self.ite_precedes(h, f) {
+                let tmp = f;
+                f = h.neg();
+                h = tmp.neg();
+            }
+        } else if h.is_false() {
+            // ite(f, g, F) = ite(g, f, F)
+            if self.ite_precedes(g, f) {
+                mem::swap(&mut f, &mut g);
+            }
+        } else if h.is_true() {
+            // ite(f, g, T) = ite(!g, !f, T)
+            if self.ite_precedes(g, f) {
+                let tmp = f;
+                f = g.neg();
+                g = tmp.neg();
+            }
+        } else if g == h.neg() {
+            // ite(f, g, !g) = ite(g, f, !f)
+            if self.ite_precedes(g, f) {
+                mem::swap(&mut f, &mut g);
+                h = g.neg();
+            }
+        }
+        // the condition and then-branch are always regular
+        if f.is_compl() {
+            f = f.neg();
+            mem::swap(&mut g, &mut h);
+        }
+        let compl = g.is_compl();
+        if compl {
+            g = g.neg();
+            h = h.neg();
+        }
+
+        // check the cache
+        if let Some(v) = self.ite_table.get(f, g, h) {
+            return if compl { v.neg() } else { v };
+        }
+
+        // find the top variable and recurse on the cofactors
+        let mut index = f.label();
+        for &p in [g, h].iter() {
+            if !p.is_const() && self.get_order().lt(p.label(), index) {
+                index = p.label();
+            }
+        }
+        let (fnv, fv) = self.top_cofactors(f, index);
+        let (gnv, gv) = self.top_cofactors(g, index);
+        let (hnv, hv) = self.top_cofactors(h, index);
+        let t = self.ite(fv, gv, hv);
+        let e = self.ite(fnv, gnv, hnv);
+        let r = if t == e {
+            t
+        } else {
+            self.get_or_insert(BddNode::new(e, t, index))
+        };
+        self.ite_table.insert(f, g, h, r);
+        if compl { r.neg() } else { r }
+    }
+
+
+    /// Returns the pair `(ptr | lbl = F, ptr | lbl = T)` for a `ptr` whose top
+    /// variable is at or below `lbl` in the order
+    pub fn top_cofactors(&self, ptr: BddPtr, lbl: VarLabel) -> (BddPtr, BddPtr) {
+        let ptr = self.resolve(ptr);
+        if ptr.is_const() || ptr.label() != lbl {
+            return (ptr, ptr);
+        }
+        let n = self.deref(ptr).into_node();
+        if ptr.is_compl() {
+            (n.low.neg(), n.high.neg())
+        } else {
+            (n.low, n.high)
+        }
+    }
+
+    fn and_exists_rec(&mut self, f: BddPtr, g: BddPtr,
+                      vars: &HashSet<VarLabel>, last_pos: usize, set: usize,
+                      exists_seen: &mut HashMap<BddPtr, BddPtr>) -> BddPtr {
+        // base cases
+        if f.is_false() || g.is_false() || f == g.neg() {
+            return BddPtr::false_node();
+        }
+        if f.is_true() || f == g {
+            return self.exists_set_helper(g, vars, last_pos, exists_seen);
+        }
+        if g.is_true() {
+            return self.exists_set_helper(f, vars, last_pos, exists_seen);
+        }
+        let topf = self.get_order().get(f.label());
+        let topg = self.get_order().get(g.label());
+        if topf > last_pos && topg > last_pos {
+            // no quantified variables remain
+            return self.and(f, g);
+        }
+
+        // normalize the arguments to increase cache efficiency
+        let (f, g, topf, topg) = if f.regular() < g.regular() {
+            (f, g, topf, topg)
+        } else {
+            (g, f, topg, topf)
+        };
+        if let Some(v) = self.and_exists_table.get(f, g, set) {
+            return v;
+        }
+
+        let index = if topf <= topg { f.label() } else { g.label() };
+        let (fnv, fv) = self.top_cofactors(f, index);
+        let (gnv, gv) = self.top_cofactors(g, index);
+        let t = self.and_exists_rec(fv, gv, vars, last_pos, set, exists_seen);
+        let r = if vars.contains(&index) {
+            if t.is_true() {
+                t
+            } else {
+                let e = self.and_exists_rec(fnv, gnv, vars, last_pos, set, exists_seen);
+                self.or(t, e)
+            }
+        } else {
+            let e = self.and_exists_rec(fnv, gnv, vars, last_pos, set, exists_seen);
+            if t == e {
+                t
+            } else {
+                self.get_or_insert(BddNode::new(e, t, index))
+            }
+        };
+        self.and_exists_table.insert(f, g, set, r);
+        r
     }
 
+    /// Computes the relational product `exists vars. f /\ g` in a single pass,
+    /// without building the full conjunction
+    pub fn and_exists(&mut self, f: BddPtr, g: BddPtr, vars: &[VarLabel]) -> BddPtr {
+        let f = self.resolve(f);
+        let g = self.resolve(g);
+        if vars.is_empty() {
+            return self.and(f, g);
+        }
+        let last_pos = vars.iter().map(|v| self.get_order().get(*v)).max().unwrap();
+        let var_set: HashSet<VarLabel> = vars.iter().cloned().collect();
+        let set = self.and_exists_table.set_idx(vars);
+        self.and_exists_rec(f, g, &var_set, last_pos, set, &mut HashMap::new())
+    }
 
     /// An abstract transformation on a BDD which applies a transformation `f`
-    /// to all nodes for a particular variable
+    /// to all nodes for a particular variable. The result of `f` may mention
+    /// any variable, so the nodes above it are rebuilt with `ite`.
     fn map_var(&mut self,
                bdd: BddPtr,
                lbl: VarLabel,
-               seen: &mut HashSet<BddPtr>,
+               seen: &mut HashMap<BddPtr, BddPtr>,
                f:&Fn(&mut BddManager, BddPtr) -> BddPtr) -> BddPtr {
-        if seen.contains(&bdd) {
-            return bdd;
-        }
         if self.get_order().lt(lbl, bdd.label()) || bdd.is_const() {
             // we passed the variable in the order, we will never find it
-            bdd
-        } else if bdd.label() == lbl {
+            return bdd;
+        }
+        if let Some(r) = seen.get(&bdd) {
+            return *r;
+        }
+        let res = if bdd.label() == lbl {
             f(self, bdd)
         } else {
             // recurse on the children
             let n = self.deref(bdd).into_node();
             let l = self.map_var(n.low, lbl, seen, f);
             let h = self.map_var(n.high, lbl, seen, f);
-            let res = if l != n.low || h != n.high {
-                // cache and return the new BDD
-                let new_bdd = BddNode {
-                    low: l,
-                    high: h,
-                    var: bdd.label(),
-                };
-                let r = self.get_or_insert(new_bdd);
+            if l != n.low || h != n.high {
+                let v = self.var(n.var, true);
+                let r = self.ite(v, h, l);
                 if bdd.is_compl() { r.neg() } else { r }
             } else {
                 // nothing changed
                 bdd
-            };
-            seen.insert(res);
-            res
-        }
+            }
+        };
+        seen.insert(bdd, res);
+        res
     }
 
     fn cond_helper(&mut self, bdd: BddPtr, lbl: VarLabel,
@@ -422,42 +994,162 @@ impl BddManager {
 
     /// Compute the Boolean function `f | var = value`
     pub fn condition(&mut self, bdd: BddPtr, lbl: VarLabel, value: bool) -> BddPtr {
+        let bdd = self.resolve(bdd);
         self.cond_helper(bdd, lbl, value, &mut HashSet::new())
     }
 
     /// Existentially quantifies out the variable `lbl` from `f`
     pub fn exists(&mut self, bdd: BddPtr, lbl: VarLabel) -> BddPtr {
-        // TODO this can be optimized by specializing it
-        // println!("input : {}", self.print_bdd(bdd));
-        let v1 = self.condition(bdd, lbl, true);
-        // println!("cond1: {}", self.print_bdd(v1));
-        let v2 = self.condition(bdd, lbl, false);
-        // println!("cond2: {}", self.print_bdd(v2));
-        self.or(v1, v2)
+        self.exists_set(bdd, &[lbl])
+    }
+
+    fn exists_set_helper(&mut self, bdd: BddPtr, vars: &HashSet<VarLabel>,
+                         last_pos: usize,
+                         seen: &mut HashMap<BddPtr, BddPtr>) -> BddPtr {
+        if bdd.is_const() || self.get_order().get(bdd.label()) > last_pos {
+            // no quantified variable occurs below this node
+            return bdd;
+        }
+        if let Some(r) = seen.get(&bdd) {
+            return *r;
+        }
+        let n = self.deref(bdd).into_node();
+        let (low, high) = if bdd.is_compl() {
+            (n.low.neg(), n.high.neg())
+        } else {
+            (n.low, n.high)
+        };
+        let l = self.exists_set_helper(low, vars, last_pos, seen);
+        let h = self.exists_set_helper(high, vars, last_pos, seen);
+        let res = if vars.contains(&n.var) {
+            self.or(l, h)
+        } else if l == h {
+            l
+        } else {
+            self.get_or_insert(BddNode::new(l, h, n.var))
+        };
+        seen.insert(bdd, res);
+        res
+    }
+
+    /// Existentially quantifies out every variable in `vars` from `f` in a
+    /// single traversal
+    pub fn exists_set(&mut self, bdd: BddPtr, vars: &[VarLabel]) -> BddPtr {
+        let bdd = self.resolve(bdd);
+        if vars.is_empty() {
+            return bdd;
+        }
+        let last_pos = vars.iter().map(|v| self.get_order().get(*v)).max().unwrap();
+        let var_set: HashSet<VarLabel> = vars.iter().cloned().collect();
+        self.exists_set_helper(bdd, &var_set, last_pos, &mut HashMap::new())
+    }
+
+    /// Universally quantifies out every variable in `vars` from `f`
+    pub fn forall(&mut self, bdd: BddPtr, vars: &[VarLabel]) -> BddPtr {
+        self.exists_set(bdd.neg(), vars).neg()
+    }
+
+    fn unique_helper(&mut self, bdd: BddPtr, lbl: VarLabel,
+                     seen: &mut HashMap<BddPtr, BddPtr>) -> BddPtr {
+        if bdd.is_const() || self.get_order().lt(lbl, bdd.label()) {
+            // `lbl` does not occur below this node, so both cofactors agree
+            return BddPtr::false_node();
+        }
+        if let Some(r) = seen.get(&bdd) {
+            return *r;
+        }
+        let (low, high) = self.top_cofactors(bdd, bdd.label());
+        let res = if bdd.label() == lbl {
+            let eq = self.iff(low, high);
+            eq.neg()
+        } else {
+            let l = self.unique_helper(low, lbl, seen);
+            let h = self.unique_helper(high, lbl, seen);
+            if l == h {
+                l
+            } else {
+                self.get_or_insert(BddNode::new(l, h, bdd.label()))
+            }
+        };
+        seen.insert(bdd, res);
+        res
+    }
+
+    /// Computes the Boolean difference of `f` with respect to `lbl`, i.e.
+    /// `(f | lbl = T) xor (f | lbl = F)`, which is true exactly on the
+    /// assignments for which the value of `lbl` is observable
+    pub fn unique(&mut self, bdd: BddPtr, lbl: VarLabel) -> BddPtr {
+        let bdd = self.resolve(bdd);
+        self.unique_helper(bdd, lbl, &mut HashMap::new())
     }
 
     /// Relabels all instances of `old_lbl` with `new_lbl`
     pub fn relabel(&mut self, bdd: BddPtr,
                    old_lbl: VarLabel, new_lbl: VarLabel) -> BddPtr {
-        let f = |man: &mut BddManager, bdd: BddPtr| {
-            // make a new bdd, which is the same as the old one, except
-            // whose label is the new label
-            let n = man.deref(bdd).into_node();
-            let new_bdd = BddNode {
-                low: n.low,
-                high: n.high,
-                var: new_lbl,
-            };
-            let r = man.get_or_insert(new_bdd);
-            if bdd.is_compl() { r.neg() } else { r }
+        let v = self.var(new_lbl, true);
+        self.compose(bdd, old_lbl, v)
+    }
+
+    /// Substitutes `g` for every occurrence of `lbl` in `f`, i.e. computes
+    /// `f[lbl := g]`
+    pub fn compose(&mut self, f: BddPtr, lbl: VarLabel, g: BddPtr) -> BddPtr {
+        let f = self.resolve(f);
+        let g = self.resolve(g);
+        let sub = |man: &mut BddManager, bdd: BddPtr| {
+            let (low, high) = man.top_cofactors(bdd, lbl);
+            man.ite(g, high, low)
+        };
+        self.map_var(f, lbl, &mut HashMap::new(), &sub)
+    }
+
+    fn vector_compose_helper(&mut self, bdd: BddPtr,
+                             subst: &HashMap<VarLabel, BddPtr>,
+                             last_pos: usize,
+                             seen: &mut HashMap<BddPtr, BddPtr>) -> BddPtr {
+        if bdd.is_const() || self.get_order().get(bdd.label()) > last_pos {
+            // no substituted variable occurs below this node
+            return bdd;
+        }
+        if let Some(r) = seen.get(&bdd) {
+            return *r;
+        }
+        let (low, high) = self.top_cofactors(bdd, bdd.label());
+        let l = self.vector_compose_helper(low, subst, last_pos, seen);
+        let h = self.vector_compose_helper(high, subst, last_pos, seen);
+        let g = match subst.get(&bdd.label()) {
+            Some(g) => *g,
+            None => self.var(bdd.label(), true),
         };
-        self.map_var(bdd, old_lbl, &mut HashSet::new(), &f)
+        let res = self.ite(g, h, l);
+        seen.insert(bdd, res);
+        res
+    }
+
+    /// Simultaneously substitutes `subst[v]` for each variable `v` in the
+    /// domain of `subst`. Substituted BDDs may mention variables which are
+    /// themselves substituted; they are not substituted again.
+    pub fn vector_compose(&mut self, f: BddPtr, subst: &HashMap<VarLabel, BddPtr>) -> BddPtr {
+        let f = self.resolve(f);
+        if subst.is_empty() {
+            return f;
+        }
+        let last_pos = subst.keys().map(|v| self.get_order().get(*v)).max().unwrap();
+        self.vector_compose_helper(f, subst, last_pos, &mut HashMap::new())
     }
 
+    /// Simultaneously renames each variable `v` in the domain of `perm` to
+    /// `perm[v]`; the new labels may be anywhere in the order, and the
+    /// renaming may swap variables
+    pub fn permute(&mut self, f: BddPtr, perm: &HashMap<VarLabel, VarLabel>) -> BddPtr {
+        let mut subst = HashMap::new();
+        for (&old, &new) in perm.iter() {
+            subst.insert(old, self.var(new, true));
+        }
+        self.vector_compose(f, &subst)
+    }
 
-    /// evaluates the top element of the data stack on the values found in
-    /// `vars`
     pub fn eval_bdd(&self, bdd: BddPtr, assgn: &HashMap<VarLabel, bool>) -> bool {
+        let bdd = self.resolve(bdd);
         fn eval_bdd_helper(man: &BddManager, ptr: BddPtr, assgn: &HashMap<VarLabel, bool>) -> bool {
             if ptr.is_true() {
                 return true;
@@ -482,20 +1174,143 @@ impl BddManager {
         eval_bdd_helper(self, bdd, assgn)
     }
 
+    /// A lazy iterator over the paths to true in `ptr`. Each item is a cube, a
+    /// partial assignment given as literals from the top of the order down;
+    /// the cubes are pairwise disjoint and their disjunction is `ptr`.
+    pub fn cubes<'a>(&'a self, ptr: BddPtr) -> BddCubeIter<'a> {
+        let ptr = self.resolve(ptr);
+        BddCubeIter {
+            man: self,
+            stack: vec![(ptr, Vec::new())],
+        }
+    }
+
+    /// A lazy iterator over every total assignment to the variables of this
+    /// manager which satisfies `ptr`
+    pub fn models<'a>(&'a self, ptr: BddPtr) -> ModelIter<BddCubeIter<'a>> {
+        let vars = (0..self.get_order().len()).map(|v| VarLabel::new(v as u64)).collect();
+        ModelIter::new(self.cubes(ptr), vars)
+    }
+
     /// Returns true if `a` == `b`
     pub fn eq_bdd(&self, a: BddPtr, b: BddPtr) -> bool {
         // the magic of BDDs!
-        a == b
+        self.resolve(a) == self.resolve(b)
     }
 
-    pub fn get_backing_store_stats(&self) -> BackingCacheStats {
-        self.compute_table.get_stats().clone()
+    /// The variables which `ptr` depends on, sorted by label
+    pub fn support(&self, ptr: BddPtr) -> Vec<VarLabel> {
+        let ptr = self.resolve(ptr);
+        let mut vars: Vec<VarLabel> = self.reachable_nodes(&[ptr]).iter().map(|n| n.label()).collect();
+        vars.sort_by_key(|v| v.value());
+        vars.dedup();
+        vars
     }
 
-    pub fn num_nodes(&self) -> usize {
+    /// the literals implied by the satisfiable `ptr`
+    fn essential_helper(&self, ptr: BddPtr,
+                        memo: &mut HashMap<BddPtr, HashSet<Literal>>) -> HashSet<Literal> {
+        if ptr.is_const() {
+            return HashSet::new();
+        }
+        if let Some(r) = memo.get(&ptr) {
+            return r.clone();
+        }
+        let (low, high) = self.top_cofactors(ptr, ptr.label());
+        let res = if low.is_false() {
+            let mut r = self.essential_helper(high, memo);
+            r.insert(Literal::new(ptr.label(), true));
+            r
+        } else if high.is_false() {
+            let mut r = self.essential_helper(low, memo);
+            r.insert(Literal::new(ptr.label(), false));
+            r
+        } else {
+            let l = self.essential_helper(low, memo);
+            let h = self.essential_helper(high, memo);
+            l.intersection(&h).cloned().collect()
+        };
+        memo.insert(ptr, res.clone());
+        res
+    }
+
+    /// The literals implied by `ptr`, sorted by label. An unsatisfiable `ptr`
+    /// has none.
+    pub fn essential_vars(&self, ptr: BddPtr) -> Vec<Literal> {
+        let ptr = self.resolve(ptr);
+        if ptr.is_false() {
+            return Vec::new();
+        }
+        let mut lits: Vec<Literal> = self.essential_helper(ptr, &mut HashMap::new()).into_iter().collect();
+        lits.sort_by_key(|l| l.get_label().value());
+        lits
+    }
+
+    /// true if `f <= g`; `proven` holds the pairs already known to satisfy it
+    fn leq_rec(&self, f: BddPtr, g: BddPtr, proven: &mut HashSet<(BddPtr, BddPtr)>) -> bool {
+        if f.is_false() || g.is_true() || f == g {
+            return true;
+        } else if f.is_true() || g.is_false() || f == g.neg() {
+            return false;
+        }
+        if proven.contains(&(f, g)) {
+            return true;
+        }
+        let lbl = if self.level(f) <= self.level(g) { f.label() } else { g.label() };
+        let (f_l, f_h) = self.top_cofactors(f, lbl);
+        let (g_l, g_h) = self.top_cofactors(g, lbl);
+        // stop at the first counterexample
+        let r = self.leq_rec(f_l, g_l, proven) && self.leq_rec(f_h, g_h, proven);
+        if r {
+            proven.insert((f, g));
+        }
+        r
+    }
+
+    /// true if `f` implies `g`: every model of `f` is a model of `g`. Unlike
+    /// building `f => g` with `imp`, this inserts no nodes and stops at the
+    /// first counterexample.
+    pub fn implies(&self, f: BddPtr, g: BddPtr) -> bool {
+        let f = self.resolve(f);
+        let g = self.resolve(g);
+        self.leq_rec(f, g, &mut HashSet::new())
+    }
+
+    /// true if `f <= g` in the ordering of functions by their models, which
+    /// is `implies`
+    pub fn leq(&self, f: BddPtr, g: BddPtr) -> bool {
+        self.implies(f, g)
+    }
+
+    /// true if `f` and `g` share no models
+    pub fn disjoint(&self, f: BddPtr, g: BddPtr) -> bool {
+        self.implies(f, g.neg())
+    }
+
+    pub fn get_backing_store_stats(&self) -> BackingCacheStats {
+        self.compute_table.get_stats().clone()
+    }
+
+    pub fn num_nodes(&self) -> usize {
         self.compute_table.num_nodes()
     }
 
+    /// The number of distinct nodes reachable from `roots`, where a node and
+    /// its complement count once
+    pub fn num_reachable(&self, roots: &[BddPtr]) -> usize {
+        let mut seen = HashSet::new();
+        let mut stack: Vec<BddPtr> = roots.iter().map(|r| self.resolve(*r).regular()).collect();
+        while let Some(n) = stack.pop() {
+            if n.is_const() || !seen.insert(n) {
+                continue;
+            }
+            let node = self.deref(n).into_node();
+            stack.push(node.low.regular());
+            stack.push(node.high.regular());
+        }
+        seen.len()
+    }
+
     fn count_nodes_h(&self, ptr: BddPtr, set: &mut HashSet<BddPtr>) -> usize {
         if set.contains(&ptr) || ptr.is_const() {
             return 0;
@@ -514,6 +1329,7 @@ impl BddManager {
     }
 
     pub fn count_nodes(&self, ptr: BddPtr) -> usize {
+        let ptr = self.resolve(ptr);
         self.count_nodes_h(ptr, &mut HashSet::new())
     }
 
@@ -521,20 +1337,20 @@ impl BddManager {
     /// on-the-fly smoothing. Returns a pair: the first element is the sum of
     /// the node, and the second element is the expected parent of that node; in
     /// the case of the node being the top variable, then `None` is returned
-    fn wmc_helper<T: Num + Clone + Debug + Copy>(
+    fn wmc_helper<S: Semiring>(
         &self,
         ptr: BddPtr,
-        wmc: &BddWmc<T>,
-        compute_table: &mut HashMap<BddPtr, (T, Option<VarLabel>)>
-    ) -> (T, Option<VarLabel>) {
+        weights: &HashMap<VarLabel, (S, S)>,
+        compute_table: &mut HashMap<BddPtr, (S, Option<VarLabel>)>
+    ) -> (S, Option<VarLabel>) {
         use repr::bdd::PointerType;
         match compute_table.get(&ptr) {
-            Some(a) => return *a,
+            Some(a) => return a.clone(),
             None => ()
         };
         match ptr.ptr_type() {
-            PointerType::PtrTrue => (wmc.one.clone(), Some(self.get_order().last_var())),
-            PointerType::PtrFalse => (wmc.zero.clone(), Some(self.get_order().last_var())),
+            PointerType::PtrTrue => (S::one(), Some(self.get_order().last_var())),
+            PointerType::PtrFalse => (S::zero(), Some(self.get_order().last_var())),
             PointerType::PtrNode => {
                 let order = self.get_order();
                 let bdd = self.deref(ptr).into_node();
@@ -543,57 +1359,443 @@ impl BddManager {
                 } else {
                     (bdd.low, bdd.high)
                 };
-                let (mut low_v, low_lvl_op) = self.wmc_helper(low, wmc, compute_table);
-                let (mut high_v, high_lvl_op) = self.wmc_helper(high, wmc, compute_table);
+                let (mut low_v, low_lvl_op) = self.wmc_helper(low, weights, compute_table);
+                let (mut high_v, high_lvl_op) = self.wmc_helper(high, weights, compute_table);
                 let mut low_lvl = low_lvl_op.unwrap();
                 let mut high_lvl = high_lvl_op.unwrap();
                 // smooth low
                 while order.lt(ptr.label(), low_lvl) {
-                    let (low_factor, high_factor) = wmc.var_to_val.get(&VarLabel::new(low_lvl.value())).unwrap();
-                    low_v = (low_v.clone() * (*low_factor)) + (low_v * (*high_factor));
+                    let (low_factor, high_factor) = weights.get(&VarLabel::new(low_lvl.value())).unwrap();
+                    low_v = low_v.product(low_factor).sum(&low_v.product(high_factor));
                     low_lvl = order.above(low_lvl).unwrap();
                 }
                 // smooth high
                 while order.lt(ptr.label(), high_lvl) {
-                    let (low_factor, high_factor) = wmc.var_to_val.get(&VarLabel::new(high_lvl.value())).unwrap();
-                    high_v = (high_v.clone() * (*low_factor)) + (high_v * (*high_factor));
+                    let (low_factor, high_factor) = weights.get(&VarLabel::new(high_lvl.value())).unwrap();
+                    high_v = high_v.product(low_factor).sum(&high_v.product(high_factor));
                     high_lvl = order.above(high_lvl).unwrap();
                 }
                 // compute new
-                let (low_factor, high_factor) = wmc.var_to_val.get(&VarLabel::new(bdd.var.value())).unwrap();
-                let res = (low_v * low_factor.clone()) + (high_v * high_factor.clone());
+                let (low_factor, high_factor) = weights.get(&VarLabel::new(bdd.var.value())).unwrap();
+                let res = low_v.product(low_factor).sum(&high_v.product(high_factor));
                 if order.get(ptr.label()) == 0 {
                     (res, None)
                 } else {
                     let r = (res, Some(order.above(ptr.label()).unwrap()));
-                    compute_table.insert(ptr, r);
+                    compute_table.insert(ptr, r.clone());
                     r
                 }
             }
         }
     }
 
-    /// Weighted-model count.
-    pub fn wmc<T: Num + Clone + Debug + Copy>(&self, ptr: BddPtr, params: &BddWmc<T>) -> T {
+    /// Weighted model count of `ptr` in the semiring `S`, where `weights` maps
+    /// every variable to its `(low, high)` weight
+    pub fn wmc_semiring<S: Semiring>(&self, ptr: BddPtr, weights: &HashMap<VarLabel, (S, S)>) -> S {
+        let ptr = self.resolve(ptr);
         // call wmc_helper and smooth the result
-        let (mut v, lvl_op) = self.wmc_helper(ptr, params, &mut HashMap::new());
-        if lvl_op.is_none() {
-            // no smoothing required
-            v
-        } else {
-            // need to smooth
-            let mut lvl = lvl_op;
-            let order = self.get_order();
-            while lvl.is_some() {
-                let (low_factor, high_factor) =
-                    params.var_to_val.get(&VarLabel::new(lvl.unwrap().value())).unwrap();
-                v = (v.clone() * (*low_factor)) + (v * (*high_factor));
-                lvl = order.above(lvl.unwrap());
+        let (mut v, lvl_op) = self.wmc_helper(ptr, weights, &mut HashMap::new());
+        let mut lvl = lvl_op;
+        let order = self.get_order();
+        while lvl.is_some() {
+            let (low_factor, high_factor) =
+                weights.get(&VarLabel::new(lvl.unwrap().value())).unwrap();
+            v = v.product(low_factor).sum(&v.product(high_factor));
+            lvl = order.above(lvl.unwrap());
+        }
+        v
+    }
+
+    pub fn wmc<T: Num + Clone + Debug + Copy>(&self, ptr: BddPtr, params: &BddWmc<T>) -> T {
+        self.wmc_semiring(ptr, &params.semiring_weights()).0
+    }
+
+    /// Every non-constant node reachable from `roots`, ordered from the top of
+    /// the order down. Complemented pointers are distinct nodes.
+    fn reachable_nodes(&self, roots: &[BddPtr]) -> Vec<BddPtr> {
+        let mut nodes = Vec::new();
+        let mut seen = HashSet::new();
+        let mut stack = roots.to_vec();
+        while let Some(n) = stack.pop() {
+            if n.is_const() || !seen.insert(n) {
+                continue;
             }
-            v
+            let (low, high) = self.top_cofactors(n, n.label());
+            stack.push(low);
+            stack.push(high);
+            nodes.push(n);
+        }
+        nodes.sort_by_key(|n| self.level(*n));
+        nodes
+    }
+
+    /// Computes the weighted model count of `ptr` together with its partial
+    /// derivatives with respect to the `(low, high)` weight of every variable.
+    /// The counts below each node are taken from the compute table of
+    /// `wmc_helper`; a single top-down pass then accumulates the weight of the
+    /// paths reaching each node.
+    pub fn wmc_grad<T: Num + Clone + Debug + Copy>(&self, ptr: BddPtr, params: &BddWmc<T>)
+                                                   -> (T, HashMap<VarLabel, (T, T)>) {
+        let ptr = self.resolve(ptr);
+        let order = self.get_order();
+        let weights: Vec<(T, T)> = (0..order.len())
+            .map(|pos| params.var_to_val[&order.var_at_pos(pos)])
+            .collect();
+        // the weighted model count of true over the levels in [a, b)
+        let smooth = |a: usize, b: usize| {
+            let mut r = T::one();
+            for &(low, high) in weights[a..b].iter() {
+                r = r * (low + high);
+            }
+            r
+        };
+
+        // bottom-up: the weighted model count of each node over the levels at
+        // or below it
+        let semiring_weights: HashMap<VarLabel, (RealSemiring<T>, RealSemiring<T>)> = params
+            .var_to_val
+            .iter()
+            .map(|(k, &(low, high))| (*k, (RealSemiring(low), RealSemiring(high))))
+            .collect();
+        let mut compute_table = HashMap::new();
+        let (RealSemiring(root_v), _) = self.wmc_helper(ptr, &semiring_weights, &mut compute_table);
+        let below = |n: BddPtr| {
+            if n.is_true() {
+                T::one()
+            } else if n.is_false() {
+                T::zero()
+            } else if n == ptr {
+                // nodes at the top level are not stored in the table
+                root_v
+            } else {
+                (compute_table[&n].0).0
+            }
+        };
+
+        // the derivative of an edge which skips the levels in [a, b) with
+        // respect to each of the skipped variables; `coeff` is the weight of
+        // every path through the edge, excluding the skipped levels
+        let mut grad: Vec<(T, T)> = weights.iter().map(|_| (T::zero(), T::zero())).collect();
+        let gap = |grad: &mut Vec<(T, T)>, a: usize, b: usize, coeff: T| {
+            for (k, g) in grad.iter_mut().enumerate().take(b).skip(a) {
+                let d = coeff * smooth(a, k) * smooth(k + 1, b);
+                *g = (g.0 + d, g.1 + d);
+            }
+        };
+
+        // top-down: the weighted count of the paths from the root to each node
+        let mut above: HashMap<BddPtr, T> = HashMap::new();
+        above.insert(ptr, smooth(0, self.level(ptr)));
+        gap(&mut grad, 0, self.level(ptr), below(ptr));
+        for &n in self.reachable_nodes(&[ptr]).iter() {
+            let lvl = self.level(n);
+            let (low, high) = self.top_cofactors(n, n.label());
+            let (wl, wh) = weights[lvl];
+            let a = above[&n];
+            for &(c, w, is_high) in [(low, wl, false), (high, wh, true)].iter() {
+                let c_lvl = self.level(c);
+                let d = a * smooth(lvl + 1, c_lvl) * below(c);
+                if is_high {
+                    grad[lvl].1 = grad[lvl].1 + d;
+                } else {
+                    grad[lvl].0 = grad[lvl].0 + d;
+                }
+                let prev = match above.get(&c) {
+                    Some(v) => *v,
+                    None => T::zero(),
+                };
+                above.insert(c, prev + a * w * smooth(lvl + 1, c_lvl));
+                gap(&mut grad, lvl + 1, c_lvl, a * w * below(c));
+            }
+        }
+        let value = smooth(0, self.level(ptr)) * below(ptr);
+        let grad = (0..order.len()).map(|pos| (order.var_at_pos(pos), grad[pos])).collect();
+        (value, grad)
+    }
+
+    /// Computes the marginal of every variable `x` given `ptr`, i.e. the
+    /// weighted model count of `ptr /\ x` divided by that of `ptr`. Since the
+    /// count is linear in each weight, the numerator is the high weight of `x`
+    /// times the derivative of the count with respect to it. Panics if the
+    /// weighted model count of `ptr` is zero, or if `T` cannot divide by it:
+    /// a `Poly` count is only divisible when it is constant, so the marginals
+    /// of symbolic weights are computed from `wmc_grad` instead.
+    pub fn marginals<T: Num + Clone + Debug + Copy>(&self, ptr: BddPtr, params: &BddWmc<T>)
+                                                    -> HashMap<VarLabel, T> {
+        let (z, grad) = self.wmc_grad(ptr, params);
+        assert!(!z.is_zero(), "marginals of a BDD with a weighted model count of zero");
+        grad.into_iter()
+            .map(|(lbl, (_, d_high))| (lbl, params.var_to_val[&lbl].1 * d_high / z))
+            .collect()
+    }
+
+    /// Rebuilds `ptr`, which belongs to `other`, in this manager
+    fn transfer(&mut self, other: &BddManager, ptr: BddPtr,
+                seen: &mut HashMap<BddPtr, BddPtr>) -> BddPtr {
+        if ptr.is_const() {
+            return ptr;
+        }
+        if let Some(r) = seen.get(&ptr) {
+            return *r;
+        }
+        let (low, high) = other.top_cofactors(ptr, ptr.label());
+        let l = self.transfer(other, low, seen);
+        let h = self.transfer(other, high, seen);
+        let v = self.var(ptr.label(), true);
+        let res = self.ite(v, h, l);
+        seen.insert(ptr, res);
+        res
+    }
+
+    /// the MAP weight of `ptr` over the levels at or below it, where the
+    /// first `num_query` levels are maximized and the rest are summed
+    fn map_helper<T: Num + Clone + Debug + Copy + PartialOrd>(
+        &self, ptr: BddPtr, weights: &[(T, T)], num_query: usize,
+        compute_table: &mut HashMap<BddPtr, T>) -> T {
+        if ptr.is_true() {
+            return T::one();
+        } else if ptr.is_false() {
+            return T::zero();
+        }
+        if let Some(v) = compute_table.get(&ptr) {
+            return *v;
+        }
+        let lvl = self.level(ptr);
+        let (low, high) = self.top_cofactors(ptr, ptr.label());
+        let (wl, wh) = weights[lvl];
+        let l = wl * map_smooth(weights, num_query, lvl + 1, self.level(low))
+            * self.map_helper(low, weights, num_query, compute_table);
+        let h = wh * map_smooth(weights, num_query, lvl + 1, self.level(high))
+            * self.map_helper(high, weights, num_query, compute_table);
+        let res = if lvl < num_query { max_weight(l, h) } else { l + h };
+        compute_table.insert(ptr, res);
+        res
+    }
+
+    /// MAP query for a manager whose first `num_query` levels are exactly the
+    /// query variables
+    fn map_constrained<T: Num + Clone + Debug + Copy + PartialOrd>(
+        &self, ptr: BddPtr, params: &BddWmc<T>, num_query: usize)
+        -> Option<(HashMap<VarLabel, bool>, T)> {
+        if ptr.is_false() {
+            return None;
+        }
+        let order = self.get_order();
+        let weights: Vec<(T, T)> = (0..order.len())
+            .map(|pos| params.var_to_val[&order.var_at_pos(pos)])
+            .collect();
+        let mut compute_table = HashMap::new();
+        let value = map_smooth(&weights, num_query, 0, self.level(ptr))
+            * self.map_helper(ptr, &weights, num_query, &mut compute_table);
+
+        // decode the maximizing assignment top-down; skipped query variables
+        // take their heavier value
+        let mut assgn = HashMap::new();
+        let mut cur = ptr;
+        for pos in 0..num_query {
+            let (wl, wh) = weights[pos];
+            if cur.is_const() || self.level(cur) > pos {
+                assgn.insert(order.var_at_pos(pos), wh > wl);
+                continue;
+            }
+            let (low, high) = self.top_cofactors(cur, cur.label());
+            let l = wl * map_smooth(&weights, num_query, pos + 1, self.level(low))
+                * self.map_helper(low, &weights, num_query, &mut compute_table);
+            let h = wh * map_smooth(&weights, num_query, pos + 1, self.level(high))
+                * self.map_helper(high, &weights, num_query, &mut compute_table);
+            let take_high = if h == l { !high.is_false() } else { h > l };
+            assgn.insert(order.var_at_pos(pos), take_high);
+            cur = if take_high { high } else { low };
+        }
+        Some((assgn, value))
+    }
+
+    /// Maximum a-posteriori query: finds the assignment to `query_vars` which
+    /// maximizes the weighted model count of `ptr` after summing out every
+    /// other variable. Returns the assignment along with its weight, or `None`
+    /// if `ptr` is unsatisfiable or a query variable is not in this manager.
+    /// Exact MAP requires the query variables to come first in the order; if
+    /// they do not, `ptr` is first rebuilt in a scratch manager with such an
+    /// order.
+    pub fn map<T: Num + Clone + Debug + Copy + PartialOrd>(
+        &self, ptr: BddPtr, params: &BddWmc<T>, query_vars: &[VarLabel])
+        -> Option<(HashMap<VarLabel, bool>, T)> {
+        let ptr = self.resolve(ptr);
+        let order = self.get_order();
+        if query_vars.iter().any(|v| v.value() as usize >= order.len()) {
+            return None;
+        }
+        let query: HashSet<VarLabel> = query_vars.iter().cloned().collect();
+        let constrained = (0..order.len())
+            .all(|pos| (pos < query.len()) == query.contains(&order.var_at_pos(pos)));
+        if constrained {
+            return self.map_constrained(ptr, params, query.len());
+        }
+        // query variables first, otherwise preserving the current order
+        let (mut new_order, rest): (Vec<VarLabel>, Vec<VarLabel>) = (0..order.len())
+            .map(|pos| order.var_at_pos(pos))
+            .partition(|v| query.contains(v));
+        new_order.extend(rest);
+        let mut man = BddManager::new(VarOrder::new(new_order));
+        let new_ptr = man.transfer(self, ptr, &mut HashMap::new());
+        man.map_constrained(new_ptr, params, query.len())
+    }
+
+    /// Most probable explanation: the highest-weight total assignment which
+    /// satisfies `ptr`, along with its weight, or `None` if `ptr` is
+    /// unsatisfiable
+    pub fn mpe<T: Num + Clone + Debug + Copy + PartialOrd>(
+        &self, ptr: BddPtr, params: &BddWmc<T>) -> Option<(HashMap<VarLabel, bool>, T)> {
+        let ptr = self.resolve(ptr);
+        self.map_constrained(ptr, params, self.get_order().len())
+    }
+
+    /// Precomputes the weighted model count of every node reachable from
+    /// `roots`, where `weights` maps every variable to its `(low, high)` weight
+    pub fn sample_table(&self, roots: &[BddPtr],
+                        weights: &HashMap<VarLabel, (f64, f64)>) -> BddSampleTable {
+        let order = self.get_order();
+        let mut table = BddSampleTable {
+            weights: (0..order.len()).map(|pos| weights[&order.var_at_pos(pos)]).collect(),
+            counts: HashMap::new(),
+        };
+        let roots: Vec<BddPtr> = roots.iter().map(|r| self.resolve(*r)).collect();
+        // bottom-up, so that both children are counted before their parent
+        for &n in self.reachable_nodes(&roots).iter().rev() {
+            let lvl = self.level(n);
+            let (low, high) = self.top_cofactors(n, n.label());
+            let (wl, wh) = table.weights[lvl];
+            let c = wl * map_smooth(&table.weights, 0, lvl + 1, self.level(low)) * table.count(low)
+                + wh * map_smooth(&table.weights, 0, lvl + 1, self.level(high)) * table.count(high);
+            table.counts.insert(n, c);
+        }
+        table
+    }
+
+    /// Draws a total assignment to every variable in this manager which
+    /// satisfies `ptr`, with probability proportional to its weight. `ptr`
+    /// must be satisfiable and reachable from the roots of `table`.
+    pub fn sample_draw<R: Rng>(&self, ptr: BddPtr, table: &BddSampleTable,
+                               rng: &mut R) -> Vec<Literal> {
+        let ptr = self.resolve(ptr);
+        let order = self.get_order();
+        let mut lits = Vec::with_capacity(order.len());
+        // each skipped level is free, so it is drawn independently
+        let draw_free = |lits: &mut Vec<Literal>, rng: &mut R, a: usize, b: usize| {
+            for pos in a..b {
+                let (wl, wh) = table.weights[pos];
+                lits.push(Literal::new(order.var_at_pos(pos), rng.next_f64() * (wl + wh) < wh));
+            }
+        };
+        let mut cur = ptr;
+        let mut lvl = 0;
+        while !cur.is_const() {
+            let cur_lvl = self.level(cur);
+            draw_free(&mut lits, rng, lvl, cur_lvl);
+            let (low, high) = self.top_cofactors(cur, cur.label());
+            let (wl, wh) = table.weights[cur_lvl];
+            let l = wl * map_smooth(&table.weights, 0, cur_lvl + 1, self.level(low)) * table.count(low);
+            let h = wh * map_smooth(&table.weights, 0, cur_lvl + 1, self.level(high)) * table.count(high);
+            let take_high = rng.next_f64() * (l + h) < h;
+            lits.push(Literal::new(cur.label(), take_high));
+            cur = if take_high { high } else { low };
+            lvl = cur_lvl + 1;
+        }
+        draw_free(&mut lits, rng, lvl, order.len());
+        lits
+    }
+
+    /// An endless iterator of independent samples from the models of `ptr`,
+    /// each drawn with probability proportional to its weight under `params`.
+    /// The counts are computed once up front, after which each sample takes
+    /// a single pass down the BDD. Yields nothing if `ptr` is unsatisfiable.
+    pub fn sample<'a, R: Rng>(&'a self, ptr: BddPtr, params: &BddWmc<f64>,
+                              rng: &'a mut R) -> BddSampleIter<'a, R> {
+        BddSampleIter {
+            man: self,
+            ptr,
+            table: self.sample_table(&[ptr], &params.var_to_val),
+            rng,
         }
     }
 
+    /// Samples the models of `ptr` uniformly at random; see `sample`
+    pub fn sample_uniform<'a, R: Rng>(&'a self, ptr: BddPtr,
+                                      rng: &'a mut R) -> BddSampleIter<'a, R> {
+        let order = self.get_order();
+        let weights = (0..order.len()).map(|pos| (order.var_at_pos(pos), (1.0, 1.0))).collect();
+        self.sample(ptr, &BddWmc::new_with_default(0.0, 1.0, weights), rng)
+    }
+
+    /// the position of `ptr` in the order; constants are below every variable
+    fn level(&self, ptr: BddPtr) -> usize {
+        if ptr.is_const() {
+            self.get_order().len()
+        } else {
+            self.get_order().get(ptr.label())
+        }
+    }
+
+    /// counts the models of `ptr` over the counted variables at or below its
+    /// level; `below[l]` is the number of counted variables at level `l` or
+    /// deeper
+    fn model_count_helper(&self, ptr: BddPtr, below: &[usize],
+                          compute_table: &mut HashMap<BddPtr, BigUint>) -> BigUint {
+        if ptr.is_true() {
+            return BigUint::one();
+        } else if ptr.is_false() {
+            return BigUint::zero();
+        }
+        if let Some(a) = compute_table.get(&ptr) {
+            return a.clone();
+        }
+        let lvl = self.level(ptr);
+        let (low, high) = self.top_cofactors(ptr, ptr.label());
+        // smooth over the counted levels skipped by each child
+        let low_v = self.model_count_helper(low, below, compute_table)
+            << (below[lvl + 1] - below[self.level(low)]);
+        let high_v = self.model_count_helper(high, below, compute_table)
+            << (below[lvl + 1] - below[self.level(high)]);
+        let res = low_v + high_v;
+        compute_table.insert(ptr, res.clone());
+        res
+    }
+
+    fn model_count_below(&self, ptr: BddPtr, below: &[usize]) -> BigUint {
+        self.model_count_helper(ptr, below, &mut HashMap::new())
+            << (below[0] - below[self.level(ptr)])
+    }
+
+    /// Counts the models of `ptr` over every variable in this manager
+    pub fn model_count(&self, ptr: BddPtr) -> BigUint {
+        let ptr = self.resolve(ptr);
+        let n = self.get_order().len();
+        let below: Vec<usize> = (0..n + 1).map(|l| n - l).collect();
+        self.model_count_below(ptr, &below)
+    }
+
+    /// Counts the models of `ptr` over the variables in `vars`; duplicates
+    /// are ignored. Returns `None` if a label is not in this manager or if
+    /// `ptr` depends on a variable outside `vars`.
+    pub fn model_count_vars(&self, ptr: BddPtr, vars: &[VarLabel]) -> Option<BigUint> {
+        let ptr = self.resolve(ptr);
+        let order = self.get_order();
+        let n = order.len();
+        if vars.iter().any(|v| v.value() as usize >= n) {
+            return None;
+        }
+        let in_set: HashSet<VarLabel> = vars.iter().cloned().collect();
+        if self.support(ptr).iter().any(|v| !in_set.contains(v)) {
+            return None;
+        }
+        let mut below = vec![0; n + 1];
+        for l in (0..n).rev() {
+            let counted = in_set.contains(&order.var_at_pos(l));
+            below[l] = below[l + 1] + if counted { 1 } else { 0 };
+        }
+        Some(self.model_count_below(ptr, &below))
+    }
+
     pub fn from_cnf(&mut self, cnf: &Cnf) -> BddPtr {
         let mut cvec: Vec<BddPtr> = Vec::with_capacity(cnf.clauses().len());
         for lit_vec in cnf.clauses().iter() {
@@ -702,6 +1904,295 @@ fn test_wmc_smooth2() {
     assert_eq!(wmc, 1440);
 }
 
+#[test]
+fn test_model_count() {
+    let mut man = BddManager::new_default_order(4);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v2 = man.var(VarLabel::new(2), true);
+    let f = man.or(v0, v2);
+    assert_eq!(man.model_count(f), BigUint::from(12u64));
+    assert_eq!(man.model_count(f.neg()), BigUint::from(4u64));
+    assert_eq!(man.model_count(BddPtr::true_node()), BigUint::from(16u64));
+    assert_eq!(man.model_count(BddPtr::false_node()), BigUint::zero());
+    let vars = [VarLabel::new(0), VarLabel::new(2)];
+    assert_eq!(man.model_count_vars(f, &vars), Some(BigUint::from(3u64)));
+    let dup = [VarLabel::new(0), VarLabel::new(2), VarLabel::new(0), VarLabel::new(1)];
+    assert_eq!(man.model_count_vars(f, &dup), Some(BigUint::from(6u64)));
+    assert_eq!(man.model_count_vars(f, &[VarLabel::new(0)]), None);
+    assert_eq!(man.model_count_vars(f, &[VarLabel::new(0), VarLabel::new(2), VarLabel::new(7)]), None);
+    // counts which overflow a u64
+    let mut man = BddManager::new_default_order(100);
+    let v0 = man.var(VarLabel::new(0), true);
+    let expected: BigUint = BigUint::one() << 99;
+    assert_eq!(man.model_count(v0), expected);
+}
+
+#[test]
+fn test_wmc_semiring() {
+    let mut man = BddManager::new_default_order(3);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v2 = man.var(VarLabel::new(2), true);
+    let f = man.or(v0, v2);
+    let probs = [(0.6, 0.4), (0.5, 0.5), (0.1, 0.9)];
+    let real: HashMap<VarLabel, (RealSemiring<f64>, RealSemiring<f64>)> = probs.iter().enumerate()
+        .map(|(i, &(l, h))| (VarLabel::new(i as u64), (RealSemiring(l), RealSemiring(h))))
+        .collect();
+    let p = man.wmc_semiring(f, &real).0;
+    assert!((p - (1.0 - 0.6 * 0.1)).abs() < 1e-12);
+
+    let log: HashMap<VarLabel, (LogSemiring, LogSemiring)> = probs.iter().enumerate()
+        .map(|(i, &(l, h))| (VarLabel::new(i as u64), (LogSemiring(l.ln()), LogSemiring(h.ln()))))
+        .collect();
+    assert!((man.wmc_semiring(f, &log).0.exp() - p).abs() < 1e-12);
+
+    // the most probable model is 0 = F, 2 = T, with 1 arbitrary
+    let max: HashMap<VarLabel, (MaxTimesSemiring, MaxTimesSemiring)> = probs.iter().enumerate()
+        .map(|(i, &(l, h))| (VarLabel::new(i as u64), (MaxTimesSemiring(l), MaxTimesSemiring(h))))
+        .collect();
+    assert!((man.wmc_semiring(f, &max).0 - 0.6 * 0.5 * 0.9).abs() < 1e-12);
+
+    // the cheapest model sets exactly one of 0 or 2, whichever costs less
+    let costs = [(0.0, 3.0), (1.0, 0.0), (0.0, 2.0)];
+    let trop: HashMap<VarLabel, (TropicalSemiring, TropicalSemiring)> = costs.iter().enumerate()
+        .map(|(i, &(l, h))| (VarLabel::new(i as u64), (TropicalSemiring(l), TropicalSemiring(h))))
+        .collect();
+    assert_eq!(man.wmc_semiring(f, &trop).0, 2.0);
+
+    // satisfiable only if one of 0 or 2 may be true
+    let mut sat = hashmap!{VarLabel::new(0) => (BoolSemiring(true), BoolSemiring(false)),
+                           VarLabel::new(1) => (BoolSemiring(true), BoolSemiring(true)),
+                           VarLabel::new(2) => (BoolSemiring(true), BoolSemiring(false))};
+    assert_eq!(man.wmc_semiring(f, &sat), BoolSemiring(false));
+    sat.insert(VarLabel::new(2), (BoolSemiring(false), BoolSemiring(true)));
+    assert_eq!(man.wmc_semiring(f, &sat), BoolSemiring(true));
+}
+
+#[test]
+fn test_semiring_instances() {
+    // a small BDD with 3 of its 8 models: (0 /\ 1) \/ (!0 /\ 1 /\ 2)
+    let mut man = BddManager::new_default_order(3);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let v2 = man.var(VarLabel::new(2), true);
+    let a = man.and(v0, v1);
+    let b = man.and(v1, v2);
+    let b = man.and(v0.neg(), b);
+    let f = man.or(a, b);
+    let vars = [VarLabel::new(0), VarLabel::new(1), VarLabel::new(2)];
+    let probs = hashmap!{vars[0] => (0.7, 0.3), vars[1] => (0.4, 0.6), vars[2] => (0.2, 0.8)};
+    let params = BddWmc::new_with_default(0.0, 1.0, probs.clone());
+
+    for &g in [f, f.neg(), BddPtr::true_node(), BddPtr::false_node()].iter() {
+        // with unit weights the real semiring counts models
+        let count: HashMap<VarLabel, (RealSemiring<f64>, RealSemiring<f64>)> = vars.iter()
+            .map(|v| (*v, (RealSemiring(1.0), RealSemiring(1.0)))).collect();
+        let c = man.model_count(g);
+        assert_eq!(BigUint::from(man.wmc_semiring(g, &count).0 as u64), c);
+        let p = man.wmc(g, &params);
+        let real: HashMap<VarLabel, (RealSemiring<f64>, RealSemiring<f64>)> = probs.iter()
+            .map(|(k, &(l, h))| (*k, (RealSemiring(l), RealSemiring(h)))).collect();
+        assert!((man.wmc_semiring(g, &real).0 - p).abs() < 1e-12);
+
+        // the boolean semiring decides satisfiability
+        let sat: HashMap<VarLabel, (BoolSemiring, BoolSemiring)> = vars.iter()
+            .map(|v| (*v, (BoolSemiring(true), BoolSemiring(true)))).collect();
+        assert_eq!(man.wmc_semiring(g, &sat).0, !c.is_zero());
+
+        // max-times finds the weight of the most probable model
+        let max: HashMap<VarLabel, (MaxTimesSemiring, MaxTimesSemiring)> = probs.iter()
+            .map(|(k, &(l, h))| (*k, (MaxTimesSemiring(l), MaxTimesSemiring(h)))).collect();
+        let best = man.mpe(g, &params).map(|(_, w)| w).unwrap_or(0.0);
+        assert!((man.wmc_semiring(g, &max).0 - best).abs() < 1e-12);
+
+        // log-space agrees with the real count
+        let log: HashMap<VarLabel, (LogSemiring, LogSemiring)> = probs.iter()
+            .map(|(k, &(l, h))| (*k, (LogSemiring(l.ln()), LogSemiring(h.ln())))).collect();
+        assert!((man.wmc_semiring(g, &log).0.exp() - p).abs() < 1e-12);
+
+        // with unit costs for true, tropical finds the fewest true variables
+        // of any model
+        let trop: HashMap<VarLabel, (TropicalSemiring, TropicalSemiring)> = vars.iter()
+            .map(|v| (*v, (TropicalSemiring(0.0), TropicalSemiring(1.0)))).collect();
+        let fewest = man.models(g)
+            .map(|m| m.values().filter(|b| **b).count() as f64)
+            .fold(f64::INFINITY, f64::min);
+        assert_eq!(man.wmc_semiring(g, &trop).0, fewest);
+    }
+}
+
+#[test]
+fn test_wmc_polynomial() {
+    use repr::polynomial::{self, Poly};
+    let mut man = BddManager::new_default_order(3);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v2 = man.var(VarLabel::new(2), true);
+    let f = man.or(v0, v2);
+    polynomial::scope(|| {
+        let p = Poly::param("p");
+        let q = Poly::param("q");
+        let one = Poly::one();
+        let weights = hashmap!{VarLabel::new(0) => (one - p, p),
+                               VarLabel::new(1) => (one, one),
+                               VarLabel::new(2) => (one - q, q)};
+        let params = BddWmc::new_with_default(Poly::zero(), one, weights);
+        let r = man.wmc(f, &params);
+        // 2 * (1 - (1 - p) * (1 - q))
+        assert_eq!(r.monomials(), (Poly::constant(2.0) * (p + q - p * q)).monomials());
+        for &(pv, qv) in [(0.1, 0.2), (0.5, 0.5), (0.9, 0.3)].iter() {
+            let values = hashmap!{String::from("p") => pv, String::from("q") => qv};
+            let float_params = BddWmc::new_with_default(0.0, 1.0, hashmap!{
+                VarLabel::new(0) => (1.0 - pv, pv),
+                VarLabel::new(1) => (1.0, 1.0),
+                VarLabel::new(2) => (1.0 - qv, qv)});
+            assert!((r.eval(&values) - man.wmc(f, &float_params)).abs() < 1e-12);
+        }
+    });
+}
+
+#[test]
+fn test_marginals() {
+    let mut man = BddManager::new_default_order(4);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let v3 = man.var(VarLabel::new(3), true);
+    // (0 /\ 1) \/ !3
+    let a = man.and(v0, v1);
+    let f = man.or(a, v3.neg());
+    let weights: HashMap<VarLabel, (f64, f64)> = hashmap!{VarLabel::new(0) => (0.25, 0.75),
+                           VarLabel::new(1) => (0.5, 0.5),
+                           VarLabel::new(2) => (0.875, 0.125),
+                           VarLabel::new(3) => (0.625, 0.375)};
+    let params = BddWmc::new_with_default(0.0, 1.0, weights.clone());
+    let z = man.wmc(f, &params);
+    let marg = man.marginals(f, &params);
+    assert_eq!(marg.len(), 4);
+    for i in 0..4 {
+        let lbl = VarLabel::new(i);
+        // the weights of each variable sum to one, so conditioning on `lbl`
+        // leaves a count in which it is smoothed out
+        let cond = man.condition(f, lbl, true);
+        let expected = weights[&lbl].1 * man.wmc(cond, &params) / z;
+        assert!((marg[&lbl] - expected).abs() < 1e-9,
+                "marginal of {:?} is {} but expected {}", lbl, marg[&lbl], expected);
+    }
+    // given !3 everything else is independent
+    let marg = man.marginals(v3.neg(), &params);
+    assert!((marg[&VarLabel::new(0)] - 0.75).abs() < 1e-9);
+    assert!(marg[&VarLabel::new(3)].abs() < 1e-9);
+}
+
+#[test]
+fn test_wmc_grad() {
+    let mut man = BddManager::new_default_order(3);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v2 = man.var(VarLabel::new(2), true);
+    // 0 \/ 2, so wmc = (l1 + h1) * (l0 * h2 + h0 * (l2 + h2))
+    let f = man.or(v0, v2);
+    let weights = hashmap!{VarLabel::new(0) => (2,3),
+                           VarLabel::new(1) => (5,7),
+                           VarLabel::new(2) => (11,13)};
+    let params = BddWmc::new_with_default(0, 1, weights);
+    let (v, grad) = man.wmc_grad(f, &params);
+    assert_eq!(v, man.wmc(f, &params));
+    assert_eq!(v, 12 * (2 * 13 + 3 * 24));
+    assert_eq!(grad[&VarLabel::new(0)], (12 * 13, 12 * 24));
+    assert_eq!(grad[&VarLabel::new(1)], (2 * 13 + 3 * 24, 2 * 13 + 3 * 24));
+    assert_eq!(grad[&VarLabel::new(2)], (12 * 3, 12 * (2 + 3)));
+    let (v, grad) = man.wmc_grad(BddPtr::true_node(), &params);
+    assert_eq!(v, 5 * 12 * 24);
+    assert_eq!(grad[&VarLabel::new(1)], (5 * 24, 5 * 24));
+}
+
+#[test]
+fn test_mpe() {
+    let mut man = BddManager::new_default_order(3);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let v2 = man.var(VarLabel::new(2), true);
+    // (0 xor 1) /\ (1 \/ 2)
+    let a = man.xor(v0, v1);
+    let b = man.or(v1, v2);
+    let f = man.and(a, b);
+    let weights = hashmap!{VarLabel::new(0) => (0.2f64, 0.8),
+                           VarLabel::new(1) => (0.7, 0.3),
+                           VarLabel::new(2) => (0.6, 0.4)};
+    let params = BddWmc::new_with_default(0.0, 1.0, weights);
+    // candidates: (T, F, T) = 0.8 * 0.7 * 0.4, (F, T, _) = 0.2 * 0.3 * 0.6
+    let (assgn, v) = man.mpe(f, &params).unwrap();
+    assert!((v - 0.8 * 0.7 * 0.4).abs() < 1e-12);
+    assert_eq!(assgn, hashmap!{VarLabel::new(0) => true,
+                               VarLabel::new(1) => false,
+                               VarLabel::new(2) => true});
+    assert!(man.mpe(BddPtr::false_node(), &params).is_none());
+}
+
+#[test]
+fn test_sample() {
+    use rand::{SeedableRng, StdRng};
+    let mut man = BddManager::new_default_order(3);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let v2 = man.var(VarLabel::new(2), true);
+    // (0 xor 1) /\ (1 \/ 2)
+    let a = man.xor(v0, v1);
+    let b = man.or(v1, v2);
+    let f = man.and(a, b);
+    let weights = hashmap!{VarLabel::new(0) => (0.2f64, 0.8),
+                           VarLabel::new(1) => (0.7, 0.3),
+                           VarLabel::new(2) => (0.6, 0.4)};
+    let params = BddWmc::new_with_default(0.0, 1.0, weights);
+    let z = man.wmc(f, &params);
+    let mut rng: StdRng = SeedableRng::from_seed(&[0usize][..]);
+    let num_samples = 20000;
+    let mut counts: HashMap<Vec<bool>, usize> = HashMap::new();
+    for m in man.sample(f, &params, &mut rng).take(num_samples) {
+        assert!(man.eval_bdd(f, &m));
+        let key = (0..3).map(|i| m[&VarLabel::new(i)]).collect();
+        *counts.entry(key).or_insert(0) += 1;
+    }
+    // (T, F, T) has weight 0.8 * 0.7 * 0.4
+    let freq = counts[&vec![true, false, true]] as f64 / num_samples as f64;
+    assert!((freq - 0.8 * 0.7 * 0.4 / z).abs() < 0.02);
+    // uniform sampling hits each of the 3 models about equally often
+    let mut uniform: HashMap<Vec<bool>, usize> = HashMap::new();
+    for m in man.sample_uniform(f, &mut rng).take(num_samples) {
+        let key = (0..3).map(|i| m[&VarLabel::new(i)]).collect();
+        *uniform.entry(key).or_insert(0) += 1;
+    }
+    assert_eq!(uniform.len(), 3);
+    for c in uniform.values() {
+        assert!((*c as f64 / num_samples as f64 - 1.0 / 3.0).abs() < 0.02);
+    }
+    assert!(man.sample(BddPtr::false_node(), &params, &mut rng).next().is_none());
+}
+
+#[test]
+fn test_map() {
+    let mut man = BddManager::new_default_order(3);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let v2 = man.var(VarLabel::new(2), true);
+    // 0 \/ (1 /\ 2)
+    let a = man.and(v1, v2);
+    let f = man.or(v0, a);
+    let weights = hashmap!{VarLabel::new(0) => (0.5f64, 0.5),
+                           VarLabel::new(1) => (0.4, 0.6),
+                           VarLabel::new(2) => (0.3, 0.7)};
+    let params = BddWmc::new_with_default(0.0, 1.0, weights);
+    // the query variable 2 comes last in the order: for 2 = T the weight is
+    // 0.7 * (0.5 + 0.5 * 0.6), for 2 = F it is 0.3 * 0.5
+    let (assgn, v) = man.map(f, &params, &[VarLabel::new(2)]).unwrap();
+    assert!((v - 0.7 * 0.8).abs() < 1e-12);
+    assert_eq!(assgn, hashmap!{VarLabel::new(2) => true});
+    // maximizing over 0 alone sums out 1 and 2
+    let (assgn, v) = man.map(f, &params, &[VarLabel::new(0)]).unwrap();
+    assert!((v - 0.5).abs() < 1e-12);
+    assert_eq!(assgn, hashmap!{VarLabel::new(0) => true});
+    // unknown query variables are rejected
+    assert!(man.map(f, &params, &[VarLabel::new(0), VarLabel::new(3)]).is_none());
+    assert!(man.map(f, &params, &[VarLabel::new(7)]).is_none());
+}
+
 #[test]
 fn test_condition() {
     let mut man = BddManager::new_default_order(3);
@@ -769,6 +2260,256 @@ fn test_exist_compl() {
     );
 }
 
+#[test]
+fn test_exists_set() {
+    let mut man = BddManager::new_default_order(4);
+    // (0 /\ 1) \/ (!2 /\ 3)
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let v2 = man.var(VarLabel::new(2), false);
+    let v3 = man.var(VarLabel::new(3), true);
+    let a = man.and(v0, v1);
+    let b = man.and(v2, v3);
+    let f = man.or(a, b);
+    let res = man.exists_set(f, &[VarLabel::new(1), VarLabel::new(3)]);
+    let expected = man.or(v0, v2);
+    assert!(
+        man.eq_bdd(res, expected),
+        "Got:\n{}\nExpected: {}",
+        man.print_bdd(res),
+        man.print_bdd(expected)
+    );
+    // quantifying one at a time agrees
+    let e1 = man.exists(f, VarLabel::new(3));
+    let e2 = man.exists(e1, VarLabel::new(1));
+    assert!(man.eq_bdd(res, e2));
+}
+
+#[test]
+fn test_forall() {
+    let mut man = BddManager::new_default_order(3);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let v2 = man.var(VarLabel::new(2), false);
+    let a = man.or(v0, v1);
+    let f = man.and(a, v2);
+    let res = man.forall(f, &[VarLabel::new(0)]);
+    let expected = man.and(v1, v2);
+    assert!(
+        man.eq_bdd(res, expected),
+        "Got:\n{}\nExpected: {}",
+        man.print_bdd(res),
+        man.print_bdd(expected)
+    );
+    let res = man.forall(f, &[VarLabel::new(0), VarLabel::new(1)]);
+    assert!(man.is_false(res));
+}
+
+#[test]
+fn test_ite() {
+    let mut man = BddManager::new_default_order(3);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let v2 = man.var(VarLabel::new(2), true);
+    let fns = [v0, v1.neg(), v2, BddPtr::true_node(), BddPtr::false_node()];
+    for &f in fns.iter() {
+        for &g in fns.iter() {
+            for &h in fns.iter() {
+                let res = man.ite(f, g, h);
+                let fg = man.and(f, g);
+                let nfh = man.and(f.neg(), h);
+                let expected = man.or(fg, nfh);
+                assert!(
+                    man.eq_bdd(res, expected),
+                    "Got:\n{}\nExpected: {}",
+                    man.print_bdd(res),
+                    man.print_bdd(expected)
+                );
+            }
+        }
+    }
+    // equivalent triples share a canonical result
+    let a = man.ite(v0, BddPtr::true_node(), v2);
+    let b = man.ite(v2, BddPtr::true_node(), v0);
+    assert!(man.eq_bdd(a, b));
+    let a = man.ite(v1, v0, v0.neg());
+    let b = man.ite(v0, v1, v1.neg());
+    assert!(man.eq_bdd(a, b));
+}
+
+#[test]
+fn test_binary_ops() {
+    let mut man = BddManager::new_default_order(2);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let x = man.xor(v0, v1);
+    let i = man.iff(v0, v1);
+    let imp = man.imp(v0, v1);
+    let nand = man.nand(v0, v1);
+    let nor = man.nor(v0, v1);
+    assert!(man.eq_bdd(x, i.neg()));
+    for &a in [true, false].iter() {
+        for &b in [true, false].iter() {
+            let assgn = hashmap!{VarLabel::new(0) => a, VarLabel::new(1) => b};
+            assert_eq!(man.eval_bdd(x, &assgn), a != b);
+            assert_eq!(man.eval_bdd(i, &assgn), a == b);
+            assert_eq!(man.eval_bdd(imp, &assgn), !a || b);
+            assert_eq!(man.eval_bdd(nand, &assgn), !(a && b));
+            assert_eq!(man.eval_bdd(nor, &assgn), !(a || b));
+        }
+    }
+}
+
+#[test]
+fn test_support() {
+    let mut man = BddManager::new_default_order(4);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let v3 = man.var(VarLabel::new(3), true);
+    // 3 /\ !0 /\ (1 \/ !1)
+    let a = man.and(v3, v0.neg());
+    let taut = man.or(v1, v1.neg());
+    let f = man.and(a, taut);
+    assert_eq!(man.support(f), vec![VarLabel::new(0), VarLabel::new(3)]);
+    assert_eq!(man.essential_vars(f),
+               vec![Literal::new(VarLabel::new(0), false), Literal::new(VarLabel::new(3), true)]);
+    // 1 is essential to both disjuncts of (0 /\ 1) \/ (3 /\ 1)
+    let l = man.and(v0, v1);
+    let r = man.and(v3, v1);
+    let g = man.or(l, r);
+    assert_eq!(man.support(g).len(), 3);
+    assert_eq!(man.essential_vars(g), vec![Literal::new(VarLabel::new(1), true)]);
+    assert_eq!(man.essential_vars(g.neg()), vec![]);
+    assert!(man.support(BddPtr::true_node()).is_empty());
+}
+
+#[test]
+fn test_implies() {
+    let mut man = BddManager::new_default_order(3);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let v2 = man.var(VarLabel::new(2), true);
+    let a = man.and(v0, v2);
+    let o = man.or(v1, v2);
+    let x = man.xor(v0, v1);
+    let num_nodes = man.num_nodes();
+    assert!(man.implies(a, o));
+    assert!(man.implies(a, v0));
+    assert!(man.implies(a, a));
+    // the models overlap, but neither function implies the other
+    assert!(!man.implies(o, a));
+    assert!(!man.implies(v0, a));
+    assert!(!man.implies(x, o));
+    assert!(!man.implies(o, x));
+    assert!(!man.implies(a, a.neg()));
+    assert!(man.implies(BddPtr::false_node(), a));
+    assert!(!man.implies(BddPtr::true_node(), o));
+    assert_eq!(man.leq(o, a), man.implies(o, a));
+    assert!(man.leq(BddPtr::false_node(), a));
+    assert!(man.leq(a, BddPtr::true_node()));
+    assert!(!man.leq(BddPtr::true_node(), a));
+    assert!(man.disjoint(a, v2.neg()));
+    assert!(!man.disjoint(a, o));
+    assert!(man.disjoint(o.neg(), v1));
+    // the checks do not build any nodes
+    assert_eq!(man.num_nodes(), num_nodes);
+}
+
+#[test]
+fn test_apply() {
+    let mut man = BddManager::new_default_order(3);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let v2 = man.var(VarLabel::new(2), true);
+    let a = man.or(v0, v1);
+    let b = man.and(v1, v2.neg());
+    let fns = [a, a.neg(), b, v2, BddPtr::true_node(), BddPtr::false_node()];
+    for &op in BoolOp::all() {
+        for &f in fns.iter() {
+            for &g in fns.iter() {
+                let r = man.apply(op, f, g);
+                for i in 0..8 {
+                    let assgn = hashmap!{VarLabel::new(0) => i & 1 == 1,
+                                         VarLabel::new(1) => i & 2 == 2,
+                                         VarLabel::new(2) => i & 4 == 4};
+                    let expected = op.eval(man.eval_bdd(f, &assgn), man.eval_bdd(g, &assgn));
+                    assert_eq!(man.eval_bdd(r, &assgn), expected,
+                               "{:?} of\n{}\n{}\ngave {}", op,
+                               man.print_bdd(f), man.print_bdd(g), man.print_bdd(r));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_unique() {
+    let mut man = BddManager::new_default_order(3);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let v2 = man.var(VarLabel::new(2), true);
+    // f = (0 /\ 1) \/ 2
+    let a = man.and(v0, v1);
+    let f = man.or(a, v2);
+    // 0 is observable exactly when 1 /\ !2
+    let res = man.unique(f, VarLabel::new(0));
+    let expected = man.and(v1, v2.neg());
+    assert!(
+        man.eq_bdd(res, expected),
+        "Got:\n{}\nExpected: {}",
+        man.print_bdd(res),
+        man.print_bdd(expected)
+    );
+    // the negation of a function has the same Boolean difference
+    let res = man.unique(f.neg(), VarLabel::new(0));
+    assert!(man.eq_bdd(res, expected));
+    // 2 is observable exactly when !(0 /\ 1)
+    let res = man.unique(f, VarLabel::new(2));
+    assert!(man.eq_bdd(res, a.neg()));
+    // a variable not in the support is never observable
+    let res = man.unique(a, VarLabel::new(2));
+    assert!(man.is_false(res));
+    // the Boolean difference agrees with conditioning
+    let hi = man.condition(f, VarLabel::new(1), true);
+    let lo = man.condition(f, VarLabel::new(1), false);
+    let eq = man.iff(hi, lo);
+    let res = man.unique(f, VarLabel::new(1));
+    assert!(man.eq_bdd(res, eq.neg()));
+}
+
+#[test]
+fn test_and_exists() {
+    let mut man = BddManager::new_default_order(6);
+    let v: Vec<BddPtr> = (0..6).map(|i| man.var(VarLabel::new(i), true)).collect();
+    // transition relation: x3 <=> !x0, x4 <=> (x0 \/ x1), x5 <=> x2
+    let nx0 = v[0].neg();
+    let t0 = man.iff(v[3], nx0);
+    let x0_or_x1 = man.or(v[0], v[1]);
+    let t1 = man.iff(v[4], x0_or_x1);
+    let t2 = man.iff(v[5], v[2]);
+    let t = man.and(t0, t1);
+    let t = man.and(t, t2);
+    // states: x0 /\ !x2
+    let s = man.and(v[0], v[2].neg());
+    let cur = [VarLabel::new(0), VarLabel::new(1), VarLabel::new(2)];
+    let res = man.and_exists(t, s, &cur);
+    let conj = man.and(t, s);
+    let expected = man.exists_set(conj, &cur);
+    assert!(
+        man.eq_bdd(res, expected),
+        "Got:\n{}\nExpected: {}",
+        man.print_bdd(res),
+        man.print_bdd(expected)
+    );
+    // image is !x3 /\ x4 /\ !x5
+    let img = man.and(v[3].neg(), v[4]);
+    let img = man.and(img, v[5].neg());
+    assert!(man.eq_bdd(res, img));
+    // quantifying nothing is a conjunction
+    let res = man.and_exists(t, s, &[]);
+    assert!(man.eq_bdd(res, conj));
+}
+
 #[test]
 fn test_relabel() {
     let mut man = BddManager::new_default_order(3);
@@ -787,6 +2528,117 @@ fn test_relabel() {
     );
 }
 
+#[test]
+fn test_relabel_out_of_order() {
+    let mut man = BddManager::new_default_order(3);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let v2 = man.var(VarLabel::new(2), true);
+    // 1 /\ !2, relabeling 2 to 0 moves it above 1 in the order
+    let f = man.and(v1, v2.neg());
+    let res = man.relabel(f, VarLabel::new(2), VarLabel::new(0));
+    let expected = man.and(v1, v0.neg());
+    assert!(
+        man.eq_bdd(res, expected),
+        "Got:\n{}\nExpected: {}",
+        man.print_bdd(res),
+        man.print_bdd(expected)
+    );
+}
+
+#[test]
+fn test_compose() {
+    let mut man = BddManager::new_default_order(4);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let v2 = man.var(VarLabel::new(2), true);
+    let v3 = man.var(VarLabel::new(3), true);
+    // (1 xor 2) [1 := 0 /\ 3] = (0 /\ 3) xor 2
+    let f = man.xor(v1, v2);
+    let g = man.and(v0, v3);
+    let res = man.compose(f, VarLabel::new(1), g);
+    let expected = man.xor(g, v2);
+    assert!(
+        man.eq_bdd(res, expected),
+        "Got:\n{}\nExpected: {}",
+        man.print_bdd(res),
+        man.print_bdd(expected)
+    );
+    // composing with a negated function respects complemented edges
+    let res = man.compose(f.neg(), VarLabel::new(1), g.neg());
+    assert!(man.eq_bdd(res, expected));
+    // substituting a variable that does not occur is the identity
+    let res = man.compose(f, VarLabel::new(3), v0);
+    assert!(man.eq_bdd(res, f));
+}
+
+#[test]
+fn test_vector_compose() {
+    let mut man = BddManager::new_default_order(4);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let v2 = man.var(VarLabel::new(2), true);
+    let v3 = man.var(VarLabel::new(3), true);
+    // (0 /\ !1) [0 := 1, 1 := 2 \/ 3] = 1 /\ !(2 \/ 3)
+    let f = man.and(v0, v1.neg());
+    let g = man.or(v2, v3);
+    let res = man.vector_compose(f, &hashmap!{VarLabel::new(0) => v1, VarLabel::new(1) => g});
+    let expected = man.and(v1, g.neg());
+    assert!(
+        man.eq_bdd(res, expected),
+        "Got:\n{}\nExpected: {}",
+        man.print_bdd(res),
+        man.print_bdd(expected)
+    );
+}
+
+#[test]
+fn test_permute() {
+    let mut man = BddManager::new_default_order(4);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let v2 = man.var(VarLabel::new(2), true);
+    let v3 = man.var(VarLabel::new(3), true);
+    // swap 0 and 1, and rename 2 to 3
+    let a = man.and(v0, v1.neg());
+    let f = man.or(a, v2);
+    let perm = hashmap!{VarLabel::new(0) => VarLabel::new(1),
+                        VarLabel::new(1) => VarLabel::new(0),
+                        VarLabel::new(2) => VarLabel::new(3)};
+    let res = man.permute(f, &perm);
+    let b = man.and(v1, v0.neg());
+    let expected = man.or(b, v3);
+    assert!(
+        man.eq_bdd(res, expected),
+        "Got:\n{}\nExpected: {}",
+        man.print_bdd(res),
+        man.print_bdd(expected)
+    );
+}
+
+#[test]
+fn test_models() {
+    let mut man = BddManager::new_default_order(3);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let v2 = man.var(VarLabel::new(2), true);
+    // !(0 /\ 1) /\ 2
+    let a = man.and(v0, v1);
+    let f = man.and(a.neg(), v2);
+    let cubes: Vec<Vec<Literal>> = man.cubes(f).collect();
+    assert_eq!(cubes.len(), 2);
+    for c in cubes.iter() {
+        assert!(c.contains(&Literal::new(VarLabel::new(2), true)));
+    }
+    let models: Vec<HashMap<VarLabel, bool>> = man.models(f).collect();
+    assert_eq!(models.len(), 3);
+    for m in models.iter() {
+        assert!(man.eval_bdd(f, m));
+    }
+    assert_eq!(man.models(BddPtr::false_node()).count(), 0);
+    assert_eq!(man.models(BddPtr::true_node()).count(), 8);
+}
+
 #[test]
 fn test_new_var() {
     let mut man = BddManager::new_default_order(0);
@@ -845,3 +2697,212 @@ fn simple_cond() {
 
 }
 
+#[test]
+fn test_garbage_collect() {
+    let mut man = BddManager::new_default_order(4);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), false);
+    let v2 = man.var(VarLabel::new(2), true);
+    let v3 = man.var(VarLabel::new(3), true);
+    let keep = man.and(v0, v1);
+    let keep = man.or(keep, v2);
+    let r = man.ext_ref(keep);
+    let garbage = man.iff(v1, v3);
+    let garbage = man.or(garbage, v0);
+    man.and(garbage, keep);
+    let before = man.num_nodes();
+    let freed = man.garbage_collect();
+    assert!(freed > 0);
+    assert_eq!(man.num_nodes(), before - freed);
+    assert_eq!(man.num_nodes(), man.count_nodes(man.get_ext(r)));
+
+    // the protected BDD is still the same function, and rebuilding it yields
+    // the same canonical pointer
+    let keep = man.get_ext(r);
+    let assgn = hashmap!{
+        VarLabel::new(0) => true, VarLabel::new(1) => false,
+        VarLabel::new(2) => false, VarLabel::new(3) => true};
+    assert!(man.eval_bdd(keep, &assgn));
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), false);
+    let v2 = man.var(VarLabel::new(2), true);
+    let rebuilt = man.and(v0, v1);
+    let rebuilt = man.or(rebuilt, v2);
+    assert!(man.eq_bdd(keep, rebuilt));
+
+    // dropping the last reference frees everything
+    man.decref(r);
+    man.garbage_collect();
+    assert_eq!(man.num_nodes(), 0);
+}
+
+#[test]
+fn test_swap_levels() {
+    let mut man = BddManager::new_default_order(3);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), false);
+    let v2 = man.var(VarLabel::new(2), true);
+    let a = man.and(v0, v1);
+    let f = man.or(a, v2);
+    let r = man.ext_ref(f);
+    let expected = |a: &HashMap<VarLabel, bool>| {
+        (a[&VarLabel::new(0)] && !a[&VarLabel::new(1)]) || a[&VarLabel::new(2)]
+    };
+    for level in vec![0, 1, 0, 1] {
+        man.swap_levels(level);
+        let f = man.get_ext(r);
+        for i in 0..8 {
+            let assgn = hashmap!{
+                VarLabel::new(0) => i & 1 != 0,
+                VarLabel::new(1) => i & 2 != 0,
+                VarLabel::new(2) => i & 4 != 0};
+            assert_eq!(man.eval_bdd(f, &assgn), expected(&assgn),
+                       "Not eq after swap:\n{}", man.print_bdd(f));
+        }
+    }
+    // rebuilding under the new order gives the same canonical BDD
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), false);
+    let v2 = man.var(VarLabel::new(2), true);
+    let a = man.and(v0, v1);
+    let rebuilt = man.or(a, v2);
+    assert!(man.eq_bdd(man.get_ext(r), rebuilt));
+}
+
+#[test]
+fn test_reorder_sift() {
+    // (x0 /\ x3) \/ (x1 /\ x4) \/ (x2 /\ x5) is exponential in the linear
+    // order and linear when the pairs are adjacent
+    let mut man = BddManager::new_default_order(6);
+    let mut f = man.false_ptr();
+    for i in 0..3 {
+        let a = man.var(VarLabel::new(i), true);
+        let b = man.var(VarLabel::new(i + 3), true);
+        let c = man.and(a, b);
+        f = man.or(f, c);
+    }
+    let r = man.ext_ref(f);
+    // only the referenced BDD is left to be sifted
+    man.garbage_collect();
+    let before = man.count_nodes(f);
+    man.reorder_sift();
+    let f = man.get_ext(r);
+    assert!(man.count_nodes(f) < before);
+    assert_eq!(man.count_nodes(f), 6);
+    for i in 0..64 {
+        let assgn: HashMap<VarLabel, bool> =
+            (0..6).map(|v| (VarLabel::new(v), i & (1 << v) != 0)).collect();
+        let expected = (0..3).any(|v| assgn[&VarLabel::new(v)] && assgn[&VarLabel::new(v + 3)]);
+        assert_eq!(man.eval_bdd(f, &assgn), expected);
+    }
+}
+
+#[test]
+fn test_reorder_keeps_pointers() {
+    let mut man = BddManager::new_default_order(6);
+    let build = |man: &mut BddManager| -> BddPtr {
+        let mut f = man.false_ptr();
+        for i in 0..3 {
+            let a = man.var(VarLabel::new(i), true);
+            let b = man.var(VarLabel::new(i + 3), true);
+            let c = man.and(a, b);
+            f = man.or(f, c);
+        }
+        f
+    };
+    // sifting minimizes the referenced BDD; `f` itself and `g` are held by
+    // plain pointers
+    let f = build(&mut man);
+    let r = man.ext_ref(f);
+    man.garbage_collect();
+    let v1 = man.var(VarLabel::new(1), true);
+    let g = man.and(f, v1);
+    let cnt_f = man.model_count(f);
+    let cnt_g = man.model_count(g);
+    let before = man.count_nodes(f);
+    man.reorder_sift();
+    assert!(man.count_nodes(f) < before);
+    assert!(man.eq_bdd(f, man.get_ext(r)));
+    assert_eq!(man.model_count(f), cnt_f);
+    assert_eq!(man.model_count(g), cnt_g);
+    // both pointers denote the canonical BDDs of the new order
+    let rebuilt = build(&mut man);
+    assert!(man.eq_bdd(f, rebuilt));
+    let v1 = man.var(VarLabel::new(1), true);
+    let g2 = man.and(rebuilt, v1);
+    assert!(man.eq_bdd(g, g2));
+    assert!(man.leq(g, f));
+    let h = man.and(f, g.neg());
+    assert_eq!(man.model_count(h), cnt_f - cnt_g);
+}
+
+#[test]
+fn test_reorder_threshold() {
+    // the same function as in `test_reorder_sift`, as a CNF
+    let lit = |v: u64| Literal::new(VarLabel::new(v), true);
+    let mut clauses = vec![vec![lit(0), lit(1), lit(2)]];
+    for &(a, b, c) in [(0, 1, 5), (0, 4, 2), (0, 4, 5), (3, 1, 2), (3, 1, 5), (3, 4, 2), (3, 4, 5)].iter() {
+        clauses.push(vec![lit(a), lit(b), lit(c)]);
+    }
+    let cnf = Cnf::new(clauses);
+    let mut man = BddManager::new_default_order(6);
+    let f = man.from_cnf(&cnf);
+    let r = man.ext_ref(f);
+    man.garbage_collect();
+    let before = man.count_nodes(f);
+    // a BDD without a reference survives reordering
+    let v0 = man.var(VarLabel::new(0), true);
+    let v5 = man.var(VarLabel::new(5), false);
+    let g = man.and(v0, v5);
+    assert!(!man.reorder_if_needed());
+    man.set_reorder_threshold(Some(man.num_nodes()));
+    assert!(!man.reorder_if_needed());
+    man.set_reorder_threshold(Some(0));
+    assert!(man.reorder_if_needed());
+    assert!(man.count_nodes(man.get_ext(r)) < before);
+    for i in 0..64 {
+        let assgn: HashMap<VarLabel, bool> =
+            (0..6).map(|v| (VarLabel::new(v), i & (1 << v) != 0)).collect();
+        assert_eq!(man.eval_bdd(g, &assgn), assgn[&VarLabel::new(0)] && !assgn[&VarLabel::new(5)]);
+    }
+}
+
+#[test]
+fn test_to_dot() {
+    let mut man = BddManager::new_default_order(3);
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let v2 = man.var(VarLabel::new(2), true);
+    let a = man.and(v0, v1);
+    let f = man.or(a, v2).neg();
+    let dot = man.to_dot(f);
+    assert!(dot.starts_with("digraph BDD {\n"));
+    assert!(dot.ends_with("}\n"));
+    let edges = |dot: &str| -> Vec<String> {
+        dot.lines().filter(|l| l.contains(" -> ")).map(String::from).collect()
+    };
+    let compl = |e: &String| e.contains("color=red") && e.contains("arrowhead=dot");
+    // one node per variable, each on its own rank, and the single terminal
+    assert_eq!(dot.matches("rank=same").count(), 3);
+    for v in 0..3 {
+        assert_eq!(dot.matches(&format!(" [label=\"{}\"]", v)).count(), 1);
+    }
+    assert_eq!(dot.matches("t [shape=box, label=\"T\"]").count(), 1);
+    // a high and a low edge per node, and the edge to the root
+    let e = edges(&dot);
+    assert_eq!(e.len(), 7);
+    assert_eq!(e.iter().filter(|l| l.contains("style=dashed")).count(), 3);
+    assert!(e.iter().any(|l| l.contains("-> t")));
+    // the root edge is complemented, as is the low edge to false
+    assert_eq!(e.iter().filter(|l| l.contains("color=red") || l.contains("arrowhead")).count(), 2);
+    assert!(e.iter().any(|l| l.starts_with("  root -> ") && compl(l)));
+    assert!(e.iter().any(|l| l.contains("-> t ") && l.contains("style=dashed") && compl(l)));
+    // a regular root edge is unstyled
+    let e = edges(&man.to_dot(v0));
+    assert_eq!(e.len(), 3);
+    assert!(e.iter().any(|l| l.starts_with("  root -> n") && !compl(l)));
+    assert_eq!(e.iter().filter(|l| compl(l)).count(), 1);
+    // the false terminal is printed as F rather than T
+    assert_eq!(man.print_bdd(v0.neg()), "!(0, F, T)");
+    assert_eq!(man.print_bdd(BddPtr::false_node()), "F");
+}
@@ -2,13 +2,21 @@
 use manager::cache::lru::*;
 use repr::bdd::*;
 use repr::var_label::VarLabel;
+use repr::bool_op::BoolOp;
+use std::collections::HashMap;
 
 const INITIAL_CAPACITY: usize = 17; // given as a power of two
+/// initial capacity of the more specialized caches, which are used less often
+/// than the primary apply cache
+const SECONDARY_CAPACITY: usize = 12; // given as a power of two
 
-/// The top-level data structure which caches applications
+/// The top-level data structure which caches applications. Each entry is
+/// tagged with the truth table of the operator which was applied; the
+/// operator itself is not stored, since the `Lru` relies on a zeroed entry
+/// being empty, which does not hold once the key contains an enum.
 pub struct BddApplyTable {
     /// a vector of applications, indexed by the top label of the first pointer.
-    table: Vec<Lru<(BddPtr, BddPtr), BddPtr>>,
+    table: Vec<Lru<(u8, BddPtr, BddPtr), BddPtr>>,
 }
 
 impl BddApplyTable {
@@ -26,14 +34,14 @@ impl BddApplyTable {
     /// normalized by first sorting the sub-BDDs such that BDD A occurs first
     /// in the ordering; this increases cache hit rate and decreases duplicate
     /// storage
-    pub fn insert(&mut self, f: BddPtr, g: BddPtr, res: BddPtr) -> () {
+    pub fn insert(&mut self, op: BoolOp, f: BddPtr, g: BddPtr, res: BddPtr) {
         let tbl = f.var() as usize;
-        self.table[tbl].insert((f, g), res);
+        self.table[tbl].insert((op.table(), f, g), res);
     }
 
-    pub fn get(&mut self, f: BddPtr, g: BddPtr) -> Option<BddPtr> {
+    pub fn get(&mut self, op: BoolOp, f: BddPtr, g: BddPtr) -> Option<BddPtr> {
         let tbl = f.var() as usize;
-        self.table[tbl].get((f, g))
+        self.table[tbl].get((op.table(), f, g))
     }
 
     pub fn get_stats(&self) -> Vec<ApplyCacheStats> {
@@ -44,12 +52,150 @@ impl BddApplyTable {
         r
     }
 
+    /// Invalidates every cached application which mentions a pointer for
+    /// which `is_live` returns false
+    pub fn purge<F>(&mut self, is_live: &F)
+    where
+        F: Fn(BddPtr) -> bool,
+    {
+        for tbl in self.table.iter_mut() {
+            tbl.retain(&|&(_, f, g), &r| is_live(f) && is_live(g) && is_live(r));
+        }
+    }
+
+    /// Invalidates every cached application
+    pub fn clear(&mut self) {
+        for tbl in self.table.iter_mut() {
+            tbl.clear();
+        }
+    }
+
     /// Push a new application table to the back of the list
     pub fn new_last(&mut self) -> () {
         self.table.push(Lru::new(INITIAL_CAPACITY));
     }
 }
 
+/// Apply cache for `BddManager::ite`. Triples are expected to already be in
+/// standard form, so that equivalent calls share a single entry.
+pub struct BddIteTable {
+    /// a vector of applications, indexed by the top label of the condition
+    table: Vec<Lru<(BddPtr, BddPtr, BddPtr), BddPtr>>,
+}
+
+impl BddIteTable {
+    pub fn new(num_vars: usize) -> BddIteTable {
+        let mut tbl = BddIteTable {
+            table: Vec::with_capacity(num_vars),
+        };
+        for _ in 0..num_vars {
+            tbl.table.push(Lru::new(INITIAL_CAPACITY));
+        }
+        tbl
+    }
+
+    pub fn insert(&mut self, f: BddPtr, g: BddPtr, h: BddPtr, res: BddPtr) {
+        let tbl = f.var() as usize;
+        self.table[tbl].insert((f, g, h), res);
+    }
+
+    pub fn get(&mut self, f: BddPtr, g: BddPtr, h: BddPtr) -> Option<BddPtr> {
+        let tbl = f.var() as usize;
+        self.table[tbl].get((f, g, h))
+    }
+
+    /// Invalidates every cached application which mentions a pointer for
+    /// which `is_live` returns false
+    pub fn purge<F>(&mut self, is_live: &F)
+    where
+        F: Fn(BddPtr) -> bool,
+    {
+        for tbl in self.table.iter_mut() {
+            tbl.retain(&|&(f, g, h), &r| {
+                is_live(f) && is_live(g) && is_live(h) && is_live(r)
+            });
+        }
+    }
+
+    /// Invalidates every cached application
+    pub fn clear(&mut self) {
+        for tbl in self.table.iter_mut() {
+            tbl.clear();
+        }
+    }
+
+    /// Push a new application table to the back of the list
+    pub fn new_last(&mut self) {
+        self.table.push(Lru::new(INITIAL_CAPACITY));
+    }
+}
+
+/// Apply cache for conjunctions which simultaneously quantify a set of
+/// variables (see `BddManager::and_exists`). Each distinct set of quantified
+/// variables is assigned an index, and entries are keyed on `(f, g, index)`.
+pub struct BddAndExistsTable {
+    /// a vector of applications, indexed by the top label of the first pointer
+    table: Vec<Lru<(BddPtr, BddPtr, usize), BddPtr>>,
+    /// assigns an index to each sorted set of quantified variables
+    var_sets: HashMap<Vec<VarLabel>, usize>,
+}
+
+impl BddAndExistsTable {
+    pub fn new(num_vars: usize) -> BddAndExistsTable {
+        let mut tbl = BddAndExistsTable {
+            table: Vec::with_capacity(num_vars),
+            var_sets: HashMap::new(),
+        };
+        for _ in 0..num_vars {
+            tbl.table.push(Lru::new(SECONDARY_CAPACITY));
+        }
+        tbl
+    }
+
+    /// Fetch the index of the set of quantified variables `vars`, generating
+    /// a fresh one if this set has not been seen before
+    pub fn set_idx(&mut self, vars: &[VarLabel]) -> usize {
+        let mut key = vars.to_vec();
+        key.sort_by_key(|v| v.value());
+        key.dedup();
+        let fresh = self.var_sets.len();
+        *self.var_sets.entry(key).or_insert(fresh)
+    }
+
+    pub fn insert(&mut self, f: BddPtr, g: BddPtr, set: usize, res: BddPtr) {
+        let tbl = f.var() as usize;
+        self.table[tbl].insert((f, g, set), res);
+    }
+
+    pub fn get(&mut self, f: BddPtr, g: BddPtr, set: usize) -> Option<BddPtr> {
+        let tbl = f.var() as usize;
+        self.table[tbl].get((f, g, set))
+    }
+
+    /// Invalidates every cached application which mentions a pointer for
+    /// which `is_live` returns false
+    pub fn purge<F>(&mut self, is_live: &F)
+    where
+        F: Fn(BddPtr) -> bool,
+    {
+        for tbl in self.table.iter_mut() {
+            tbl.retain(&|&(f, g, _), &r| is_live(f) && is_live(g) && is_live(r));
+        }
+    }
+
+    /// Invalidates every cached application
+    pub fn clear(&mut self) {
+        for tbl in self.table.iter_mut() {
+            tbl.clear();
+        }
+    }
+
+    /// Push a new application table to the back of the list
+    pub fn new_last(&mut self) {
+        self.table.push(Lru::new(SECONDARY_CAPACITY));
+    }
+}
+
 #[test]
 fn apply_cache_simple() {
     let mut tbl = BddApplyTable::new(11);
@@ -58,7 +204,7 @@ fn apply_cache_simple() {
             let f = BddPtr::new(VarLabel::new(var), TableIndex::new(i));
             let g = BddPtr::new(VarLabel::new(var + 1), TableIndex::new(i));
             let result = BddPtr::new(VarLabel::new(var), TableIndex::new(i));
-            tbl.insert(f, g, result);
+            tbl.insert(BoolOp::And, f, g, result);
         }
     }
 
@@ -67,8 +213,9 @@ fn apply_cache_simple() {
             let f = BddPtr::new(VarLabel::new(var), TableIndex::new(i));
             let g = BddPtr::new(VarLabel::new(var + 1), TableIndex::new(i));
             let result = BddPtr::new(VarLabel::new(var), TableIndex::new(i));
-            tbl.insert(f, g, result);
-            assert_eq!(tbl.get(f, g).unwrap(), result);
+            tbl.insert(BoolOp::And, f, g, result);
+            assert_eq!(tbl.get(BoolOp::And, f, g).unwrap(), result);
+            assert_eq!(tbl.get(BoolOp::Or, f, g), None);
         }
     }
 }
@@ -161,6 +161,29 @@ where
     }
 
 
+    /// Removes every entry
+    pub fn clear(&mut self) {
+        for i in self.tbl.iter_mut() {
+            *i = None;
+        }
+    }
+
+    /// Removes every entry for which `f` returns false
+    pub fn retain<F>(&mut self, f: &F)
+    where
+        F: Fn(&K, &V) -> bool,
+    {
+        for i in self.tbl.iter_mut() {
+            let keep = match *i {
+                Some(ref e) => f(&e.key, &e.val),
+                None => true,
+            };
+            if !keep {
+                *i = None;
+            }
+        }
+    }
+
     pub fn get_stats(&self) -> ApplyCacheStats {
         // compute utilization
         let mut c = 0;
@@ -2,5 +2,6 @@ mod cache;
 
 pub mod sdd_manager;
 pub mod bdd_manager;
-mod ref_table;
+pub mod ref_table;
 pub mod var_order;
+pub mod model_iter;
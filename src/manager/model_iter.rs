@@ -0,0 +1,113 @@
+//! Expands an iterator of cubes (partial assignments) into an iterator of
+//! total assignments
+
+use repr::var_label::{VarLabel, Literal};
+use std::collections::HashMap;
+
+/// The total assignments which extend a single cube, enumerated by counting in
+/// binary over the variables which the cube leaves free
+struct CubeExpansion {
+    assgn: HashMap<VarLabel, bool>,
+    free: Vec<VarLabel>,
+    done: bool,
+}
+
+impl CubeExpansion {
+    fn new(cube: Vec<Literal>, vars: &[VarLabel]) -> CubeExpansion {
+        let mut assgn = HashMap::new();
+        for lit in cube.iter() {
+            assgn.insert(lit.get_label(), lit.get_polarity());
+        }
+        let free: Vec<VarLabel> = vars.iter().filter(|v| !assgn.contains_key(v)).cloned().collect();
+        for v in free.iter() {
+            assgn.insert(*v, false);
+        }
+        CubeExpansion {
+            assgn,
+            free,
+            done: false,
+        }
+    }
+
+    /// advance to the next assignment of the free variables, or mark the
+    /// expansion as done if they have all been visited
+    fn advance(&mut self) {
+        for v in self.free.iter() {
+            let cur = self.assgn[v];
+            self.assgn.insert(*v, !cur);
+            if !cur {
+                return;
+            }
+        }
+        self.done = true;
+    }
+}
+
+/// A lazy iterator over the total assignments to `vars` which satisfy some
+/// function, given a lazy iterator over disjoint cubes of that function
+pub struct ModelIter<I>
+where
+    I: Iterator<Item = Vec<Literal>>,
+{
+    cubes: I,
+    vars: Vec<VarLabel>,
+    cur: Option<CubeExpansion>,
+}
+
+impl<I> ModelIter<I>
+where
+    I: Iterator<Item = Vec<Literal>>,
+{
+    pub fn new(cubes: I, vars: Vec<VarLabel>) -> ModelIter<I> {
+        ModelIter {
+            cubes,
+            vars,
+            cur: None,
+        }
+    }
+}
+
+impl<I> Iterator for ModelIter<I>
+where
+    I: Iterator<Item = Vec<Literal>>,
+{
+    type Item = HashMap<VarLabel, bool>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.cur {
+                Some(ref mut e) if !e.done => {
+                    let r = e.assgn.clone();
+                    e.advance();
+                    return Some(r);
+                }
+                _ => (),
+            };
+            match self.cubes.next() {
+                None => return None,
+                Some(cube) => self.cur = Some(CubeExpansion::new(cube, &self.vars)),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_model_iter() {
+    let cubes = vec![
+        vec![Literal::new(VarLabel::new(0), true)],
+        vec![Literal::new(VarLabel::new(0), false), Literal::new(VarLabel::new(1), true)],
+    ];
+    let vars = vec![VarLabel::new(0), VarLabel::new(1), VarLabel::new(2)];
+    let models: Vec<HashMap<VarLabel, bool>> = ModelIter::new(cubes.into_iter(), vars).collect();
+    // 4 extensions of the first cube, 2 of the second
+    assert_eq!(models.len(), 6);
+    for m in models.iter() {
+        assert_eq!(m.len(), 3);
+        assert!(m[&VarLabel::new(0)] || m[&VarLabel::new(1)]);
+    }
+    for i in 0..models.len() {
+        for j in (i + 1)..models.len() {
+            assert!(models[i] != models[j]);
+        }
+    }
+}
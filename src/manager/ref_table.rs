@@ -24,6 +24,15 @@ where
     count: usize,
 }
 
+impl<InternalRef> Default for ExternalRefTable<InternalRef>
+where
+    InternalRef: Hash + PartialEq + Clone + Eq,
+{
+    fn default() -> ExternalRefTable<InternalRef> {
+        ExternalRefTable::new()
+    }
+}
+
 impl<InternalRef> ExternalRefTable<InternalRef>
 where
     InternalRef: Hash
@@ -90,6 +99,43 @@ where
         }
     }
 
+    /// the internal pointers of all external references which are still alive
+    /// (i.e., have a positive reference count)
+    pub fn roots(&self) -> Vec<InternalRef> {
+        self.ref_table
+            .values()
+            .filter(|e| e.rc > 0)
+            .map(|e| e.ptr.clone())
+            .collect()
+    }
+
+    /// drops every external reference whose counter has reached zero; these
+    /// references may no longer be dereferenced
+    pub fn remove_dead(&mut self) {
+        let dead: Vec<ExternalRef> = self.ref_table
+            .values()
+            .filter(|e| e.rc == 0)
+            .map(|e| e.r)
+            .collect();
+        for r in dead {
+            let e = self.ref_table.remove(&r).unwrap();
+            self.pointer_table.remove(&e.ptr);
+        }
+    }
+
+    /// replaces the internal pointer `p` of every external reference with
+    /// `f(p)`; `f` must be injective
+    pub fn remap<F>(&mut self, f: &F)
+    where
+        F: Fn(&InternalRef) -> InternalRef,
+    {
+        self.pointer_table.clear();
+        for e in self.ref_table.values_mut() {
+            e.ptr = f(&e.ptr);
+            self.pointer_table.insert(e.ptr.clone(), e.r);
+        }
+    }
+
     pub fn into_internal(&self, r: ExternalRef) -> InternalRef {
         match self.ref_table.get(&r) {
             None => {
@@ -1,26 +1,24 @@
 use repr::sdd::*;
 use backing_store::sdd_table::*;
-use repr::var_label::VarLabel;
+use repr::var_label::{VarLabel, Literal};
 use std::collections::{HashMap, HashSet};
+use std::cmp;
 use manager::cache::lru::*;
 use repr::cnf::Cnf;
 use quickersort;
 use util::btree::*;
 use repr::boolexpr::BoolExpr;
-
-/*
-/// SDD weighted model counting parameters
-pub struct SddWmc<T: std::fmt::Debug + Clone + Num> {
-    /// a vector of parameters for the child BDD weighted model counts
-    bdd_wmc: Vec<BddWmc<T>>
-}
-
-impl<T: std::fmt::Debug + Clone + Num> SddWmc<T> {
-    fn new(vtree: &VTree, var_to_v: Vec<T>,  zero: T, one: T) -> SddWmc {
-        // convert var_to_v into a mapping from SDD -> Value to BDD -> Value
-    }
-}
- */
+use repr::bdd::BddPtr;
+use repr::vtree;
+use manager::bdd_manager::{BddSampleTable, BddWmc};
+use rand::Rng;
+use repr::bool_op::{BoolOp, Unary};
+use manager::ref_table::{ExternalRef, ExternalRefTable};
+use manager::model_iter::ModelIter;
+use repr::semiring::*;
+use num::traits::Num;
+use std::fmt::Debug;
+use num::{BigUint, One, Zero};
 
 /// generate an even vtree by splitting a variable ordering in half `num_splits`
 /// times
@@ -43,7 +41,188 @@ pub struct SddManager {
     /// a helper structure which matches the vtree and is used for efficient LCA
     /// computation
     parent_ptr: Vec<(Option<usize>, usize)>,
-    app_cache: Vec<Lru<(SddPtr, SddPtr), SddPtr>>,
+    /// apply cache for each vtree node, keyed on the truth table of the
+    /// operator (see `BddApplyTable`)
+    app_cache: Vec<Lru<(u8, SddPtr, SddPtr), SddPtr>>,
+    /// conditioning cache for each vtree node
+    cond_cache: Vec<Lru<(SddPtr, Literal), SddPtr>>,
+    /// single-variable existential quantification cache for each vtree node
+    exists_cache: Vec<Lru<(SddPtr, VarLabel), SddPtr>>,
+    /// external references which serve as roots during garbage collection
+    ref_table: ExternalRefTable<SddPtr>,
+    /// the number of allocated nodes beyond which `minimize_if_needed`
+    /// searches for a smaller vtree
+    minimize_threshold: Option<usize>,
+}
+
+/// A lazy iterator over a set of disjoint cubes (partial assignments) whose
+/// disjunction is an SDD
+pub struct SddCubeIter<'a> {
+    man: &'a SddManager,
+    /// each entry is a partial cube, along with the SDDs which must all be
+    /// satisfied in order to extend it
+    stack: Vec<(Vec<Literal>, Vec<SddPtr>)>,
+}
+
+impl<'a> Iterator for SddCubeIter<'a> {
+    type Item = Vec<Literal>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((cube, mut goals)) = self.stack.pop() {
+            let ptr = match goals.pop() {
+                None => return Some(cube),
+                Some(p) => p,
+            };
+            if ptr.is_true() {
+                self.stack.push((cube, goals));
+            } else if ptr.is_false() {
+                continue;
+            } else if ptr.is_bdd() {
+                // expand a single BDD node, translating its label back into
+                // an SDD label
+                let bdd_man = self.man.tbl.bdd_man(ptr.vtree());
+                let bdd = ptr.as_bdd_ptr();
+                let lbl = self.man.tbl.bdd_conv(ptr.vtree())[&bdd.label()];
+                let (low, high) = bdd_man.top_cofactors(bdd, bdd.label());
+                let low = self.man.wrap_bdd(low, ptr.vtree());
+                let high = self.man.wrap_bdd(high, ptr.vtree());
+                let mut low_cube = cube.clone();
+                low_cube.push(Literal::new(lbl, false));
+                let mut low_goals = goals.clone();
+                low_goals.push(low);
+                self.stack.push((low_cube, low_goals));
+                let mut high_cube = cube;
+                high_cube.push(Literal::new(lbl, true));
+                goals.push(high);
+                self.stack.push((high_cube, goals));
+            } else {
+                // the primes are mutually exclusive, so each element
+                // contributes a disjoint set of cubes
+                for (p, s) in self.man.tbl.sdd_slice_or_panic(ptr).iter().rev() {
+                    let s = if ptr.is_compl() { s.neg() } else { *s };
+                    let mut g = goals.clone();
+                    g.push(s);
+                    g.push(*p);
+                    self.stack.push((cube.clone(), g));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// The `(low, high)` weight of every variable in each vtree leaf, in the
+/// labels of the leaf's BDD manager
+type LeafWeights<S> = HashMap<usize, HashMap<VarLabel, (S, S)>>;
+
+/// The pair `(left, right)` of children of each vtree node, or `None` for a
+/// leaf
+type VtreeChildren = Vec<Option<(usize, usize)>>;
+
+/// An endless iterator of independent samples from the models of an SDD,
+/// each drawn with probability proportional to its weight
+pub struct SddSampleIter<'a, R: Rng + 'a> {
+    man: &'a SddManager,
+    ptr: SddPtr,
+    root: usize,
+    /// the `(low, high)` weight of every variable
+    weights: HashMap<VarLabel, (f64, f64)>,
+    leaf_weights: LeafWeights<RealSemiring<f64>>,
+    smooth: Vec<RealSemiring<f64>>,
+    children: VtreeChildren,
+    /// the weighted model count of each node over its own vtree node
+    counts: HashMap<SddPtr, RealSemiring<f64>>,
+    /// the counts of the BDD nodes in each vtree leaf
+    leaf_tables: HashMap<usize, BddSampleTable>,
+    /// the variables of each vtree node
+    vars: Vec<Vec<VarLabel>>,
+    rng: &'a mut R,
+}
+
+impl<'a, R: Rng + 'a> SddSampleIter<'a, R> {
+    /// the weighted model count of `ptr` over the variables of `vtree`
+    fn count(&mut self, ptr: SddPtr, vtree: usize) -> f64 {
+        self.man
+            .wmc_smoothed(ptr, vtree, &self.leaf_weights, &self.smooth, &self.children,
+                          &mut self.counts)
+            .0
+    }
+
+    /// draws each variable of `vtree` independently
+    fn draw_free(&mut self, vtree: usize, assgn: &mut HashMap<VarLabel, bool>) {
+        for v in self.vars[vtree].iter() {
+            let (wl, wh) = self.weights[v];
+            assgn.insert(*v, self.rng.next_f64() * (wl + wh) < wh);
+        }
+    }
+
+    /// draws a model of the satisfiable `ptr` over the variables of `vtree`,
+    /// which must contain the vtree node of `ptr`
+    fn draw(&mut self, ptr: SddPtr, vtree: usize, assgn: &mut HashMap<VarLabel, bool>) {
+        if ptr.is_true() {
+            return self.draw_free(vtree, assgn);
+        }
+        if ptr.is_bdd() {
+            let leaf = ptr.vtree();
+            let lits = self.man.tbl.bdd_man(leaf).sample_draw(ptr.as_bdd_ptr(),
+                                                              &self.leaf_tables[&leaf],
+                                                              self.rng);
+            let conv = self.man.tbl.bdd_conv(leaf);
+            for l in lits.iter() {
+                assgn.insert(conv[&l.get_label()], l.get_polarity());
+            }
+        } else {
+            // choose an element in proportion to its count, then draw its
+            // prime and sub independently
+            let (l, r) = self.children[ptr.vtree()].unwrap();
+            let elems: Vec<(SddPtr, SddPtr)> = self.man
+                .tbl
+                .sdd_slice_or_panic(ptr)
+                .iter()
+                .map(|&(p, s)| (p, if ptr.is_compl() { s.neg() } else { s }))
+                .filter(|&(_, s)| !s.is_false())
+                .collect();
+            let elem_counts: Vec<f64> = elems
+                .iter()
+                .map(|&(p, s)| self.count(p, l) * self.count(s, r))
+                .collect();
+            let total = elem_counts.iter().fold(0.0, |a, b| a + b);
+            let mut target = self.rng.next_f64() * total;
+            let mut chosen = elems.len() - 1;
+            for (i, c) in elem_counts.iter().enumerate() {
+                if target < *c {
+                    chosen = i;
+                    break;
+                }
+                target -= *c;
+            }
+            let (p, s) = elems[chosen];
+            self.draw(p, l, assgn);
+            self.draw(s, r, assgn);
+        }
+        // the siblings of each node on the path up to `vtree` are free
+        let mut cur = ptr.vtree();
+        while cur != vtree {
+            let parent = self.man.parent_ptr[cur].0.unwrap();
+            let (l, r) = self.children[parent].unwrap();
+            self.draw_free(if l == cur { r } else { l }, assgn);
+            cur = parent;
+        }
+    }
+}
+
+impl<'a, R: Rng + 'a> Iterator for SddSampleIter<'a, R> {
+    type Item = HashMap<VarLabel, bool>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ptr.is_false() {
+            return None;
+        }
+        let mut assgn = HashMap::new();
+        let (ptr, root) = (self.ptr, self.root);
+        self.draw(ptr, root, &mut assgn);
+        Some(assgn)
+    }
 }
 
 /// produces a vector of pointers to vtrees such that (i) the order is given by
@@ -105,22 +284,69 @@ fn least_common_ancestor(
 }
 
 
+/// Applies the result of fixing one argument of an operator to `ptr`
+fn apply_unary(u: Unary, ptr: SddPtr) -> SddPtr {
+    match u {
+        Unary::Const(v) => SddPtr::new_const(v),
+        Unary::Id => ptr,
+        Unary::Neg => ptr.neg(),
+    }
+}
+
 /// true if `idx_a` is prime to `idx_b`
 fn is_prime(_: &VTree, idx_a: usize, idx_b: usize) -> bool {
     idx_a < idx_b
 }
 
+/// the maximum number of passes of `SddManager::minimize`
+const MINIMIZE_PASSES: usize = 4;
+
+/// the comments which open every `.sdd` file written by the SDD package
+const SDD_FILE_HEADER: &str = "c ids of sdd nodes start at 0
+c sdd nodes appear bottom-up, children before parents
+c
+c file syntax:
+c sdd count-of-sdd-nodes
+c F id-of-false-sdd-node
+c T id-of-true-sdd-node
+c L id-of-literal-sdd-node id-of-vtree literal
+c D id-of-decomposition-sdd-node id-of-vtree number-of-elements {id-of-prime id-of-sub}*
+c
+";
+
+/// Appends the literal node for `lit` at the vtree leaf `vtree` to `lines`
+/// unless it is in `lits`, and returns its id; see `write_sdd_node`
+fn write_lit(lit: Literal, vtree: usize, lits: &mut HashMap<Literal, usize>,
+             lines: &mut Vec<String>) -> usize {
+    if let Some(id) = lits.get(&lit) {
+        return *id;
+    }
+    let v = lit.get_label().value() as i64 + 1;
+    let id = lines.len();
+    lines.push(format!("L {} {} {}", id, vtree, if lit.get_polarity() { v } else { -v }));
+    lits.insert(lit, id);
+    id
+}
+
 impl SddManager {
     pub fn new(vtree: VTree) -> SddManager {
         let mut app_cache = Vec::new();
+        let mut cond_cache = Vec::new();
+        let mut exists_cache = Vec::new();
         for _ in vtree.in_order_iter() {
             app_cache.push(Lru::new(17));
+            cond_cache.push(Lru::new(12));
+            exists_cache.push(Lru::new(12));
         }
         SddManager {
             tbl: SddTable::new(&vtree),
             parent_ptr: into_parent_ptr_vec(&vtree),
             vtree: vtree,
             app_cache: app_cache,
+            cond_cache,
+            exists_cache,
+            ref_table: ExternalRefTable::new(),
+            minimize_threshold: None,
         }
     }
 
@@ -135,6 +361,501 @@ impl SddManager {
     }
 
 
+    /// Generates an external reference to `ptr`, or increments the counter of
+    /// the existing one. Only SDDs which are reachable from an external
+    /// reference with a positive count survive `garbage_collect`.
+    pub fn ext_ref(&mut self, ptr: SddPtr) -> ExternalRef {
+        self.ref_table.gen_or_inc(ptr)
+    }
+
+    pub fn incref(&mut self, r: ExternalRef) {
+        self.ref_table.incref(r)
+    }
+
+    /// Decrement the counter of `r`; once it reaches zero the referenced SDD
+    /// may be reclaimed by the next collection
+    pub fn decref(&mut self, r: ExternalRef) {
+        self.ref_table.decref(r)
+    }
+
+    /// Fetch the SDD pointed to by the external reference `r`
+    pub fn get_ext(&self, r: ExternalRef) -> SddPtr {
+        self.ref_table.into_internal(r)
+    }
+
+    /// true if `ptr` survives the sweep which follows the current mark phase
+    fn is_marked(&self, ptr: SddPtr) -> bool {
+        if ptr.is_const() {
+            true
+        } else if ptr.is_bdd() {
+            self.tbl.bdd_man(ptr.vtree()).is_marked(ptr.as_bdd_ptr())
+        } else {
+            self.tbl.is_marked_sdd(ptr)
+        }
+    }
+
+    /// Mark-and-sweep garbage collection. Walks every SDD reachable from a
+    /// live external reference, reclaims all unreachable or-nodes as well as
+    /// unreachable nodes of each vtree leaf's BDD manager, and drops all
+    /// cached applications which mention a reclaimed node. Any `SddPtr` which
+    /// is not protected by an external reference is invalid after this call.
+    /// Returns the total number of reclaimed nodes.
+    pub fn garbage_collect(&mut self) -> usize {
+        self.ref_table.remove_dead();
+        // mark the or-nodes, and gather the sub-BDD roots for each leaf
+        let mut bdd_roots: Vec<Vec<BddPtr>> = self.app_cache.iter().map(|_| Vec::new()).collect();
+        let mut stack = self.ref_table.roots();
+        while let Some(ptr) = stack.pop() {
+            if ptr.is_const() {
+                continue;
+            } else if ptr.is_bdd() {
+                bdd_roots[ptr.vtree()].push(ptr.as_bdd_ptr());
+            } else if self.tbl.mark_sdd(ptr) {
+                for &(p, s) in self.tbl.sdd_slice_or_panic(ptr).iter() {
+                    stack.push(p);
+                    stack.push(s);
+                }
+            }
+        }
+        let mut leaves = Vec::new();
+        for (idx, v) in self.vtree.in_order_iter().enumerate() {
+            if let BTree::Leaf(_) = *v {
+                leaves.push(idx);
+            }
+        }
+        for &idx in leaves.iter() {
+            self.tbl.bdd_man_mut(idx).mark(&bdd_roots[idx]);
+        }
+
+        // purge the caches before any marks are cleared
+        let mut app_cache = ::std::mem::take(&mut self.app_cache);
+        for c in app_cache.iter_mut() {
+            c.retain(&|&(_, a, b), &r| self.is_marked(a) && self.is_marked(b) && self.is_marked(r));
+        }
+        self.app_cache = app_cache;
+        let mut cond_cache = ::std::mem::take(&mut self.cond_cache);
+        for c in cond_cache.iter_mut() {
+            c.retain(&|&(a, _), &r| self.is_marked(a) && self.is_marked(r));
+        }
+        self.cond_cache = cond_cache;
+        let mut exists_cache = ::std::mem::take(&mut self.exists_cache);
+        for c in exists_cache.iter_mut() {
+            c.retain(&|&(a, _), &r| self.is_marked(a) && self.is_marked(r));
+        }
+        self.exists_cache = exists_cache;
+
+        let mut cnt = self.tbl.sweep_sdd();
+        for &idx in leaves.iter() {
+            cnt += self.tbl.bdd_man_mut(idx).sweep();
+        }
+        cnt
+    }
+
+    pub fn get_vtree(&self) -> &VTree {
+        &self.vtree
+    }
+
+    /// Rebuilds the or-node `ptr` of `old` in this manager. BDD pointers are
+    /// carried over unchanged, since the leaf BDD managers are moved rather
+    /// than rebuilt. A node whose rebuilt primes and subs still lie in the
+    /// left and right subtrees of a common vtree node is copied directly;
+    /// otherwise it is recomputed as the disjunction of its elements.
+    fn transfer(&mut self, old: &SddTable, ptr: SddPtr, leaf_map: &HashMap<usize, usize>,
+                memo: &mut HashMap<SddPtr, SddPtr>) -> SddPtr {
+        if ptr.is_const() {
+            return ptr;
+        } else if ptr.is_bdd() {
+            return SddPtr::new_bdd(ptr.as_bdd_ptr(), leaf_map[&ptr.vtree()] as u16);
+        } else if ptr.is_compl() {
+            return self.transfer(old, ptr.neg(), leaf_map, memo).neg();
+        }
+        if let Some(r) = memo.get(&ptr) {
+            return *r;
+        }
+        let mut elems = Vec::new();
+        for &(p, s) in old.sdd_slice_or_panic(ptr).iter() {
+            let p = self.transfer(old, p, leaf_map, memo);
+            let s = self.transfer(old, s, leaf_map, memo);
+            elems.push((p, s));
+        }
+        let mut lca = None;
+        for &(p, s) in elems.iter() {
+            for x in [p, s].iter().filter(|x| !x.is_const()) {
+                lca = Some(match lca {
+                    None => x.vtree(),
+                    Some(l) => least_common_ancestor(&self.parent_ptr, l, x.vtree()),
+                });
+            }
+        }
+        let is_valid = match lca {
+            Some(l) => elems.iter().all(|&(p, s)| {
+                (p.is_const() || p.vtree() < l) && (s.is_const() || s.vtree() > l)
+            }),
+            None => false,
+        };
+        let res = if is_valid {
+            self.compress(elems, lca.unwrap())
+        } else {
+            let mut r = SddPtr::new_const(false);
+            for (p, s) in elems {
+                let term = self.and(p, s);
+                r = self.or(r, term);
+            }
+            r
+        };
+        memo.insert(ptr, res);
+        res
+    }
+
+    /// Replaces the vtree with `vtree`, which must have the same leaves (as
+    /// sets of variables, in the same order) as the current one. SDDs
+    /// reachable from an external reference keep their function, and the
+    /// reference is updated to point to the rewritten SDD; as with
+    /// `garbage_collect`, every other `SddPtr` is invalidated.
+    pub fn set_vtree(&mut self, vtree: VTree) {
+        self.ref_table.remove_dead();
+        // map each leaf to the leaf of the new vtree with the same variables
+        let mut leaf_map = HashMap::new();
+        for (idx, v) in self.vtree.in_order_iter().enumerate() {
+            if let BTree::Leaf(ref vars) = *v {
+                match vtree.find_leaf_idx(&|l| l == vars) {
+                    None => panic!("leaf {:?} not found in the new vtree", vars),
+                    Some(a) => leaf_map.insert(idx, a),
+                };
+            }
+        }
+        let tbl = SddTable::with_bdd_mans(&vtree, &mut self.tbl, &leaf_map);
+        let old = ::std::mem::replace(&mut self.tbl, tbl);
+        self.parent_ptr = into_parent_ptr_vec(&vtree);
+        self.vtree = vtree;
+        // both vtrees have the same number of nodes, so the caches can be
+        // reused once emptied
+        for c in self.app_cache.iter_mut() {
+            c.clear();
+        }
+        for c in self.cond_cache.iter_mut() {
+            c.clear();
+        }
+        for c in self.exists_cache.iter_mut() {
+            c.clear();
+        }
+        let mut memo = HashMap::new();
+        for r in self.ref_table.roots() {
+            self.transfer(&old, r, &leaf_map, &mut memo);
+        }
+        self.ref_table.remap(&|p: &SddPtr| {
+            if p.is_const() {
+                *p
+            } else if p.is_bdd() {
+                SddPtr::new_bdd(p.as_bdd_ptr(), leaf_map[&p.vtree()] as u16)
+            } else if p.is_compl() {
+                memo[&p.neg()].neg()
+            } else {
+                memo[p]
+            }
+        });
+        self.garbage_collect();
+    }
+
+    /// The in-order indices of the leftmost and rightmost vtree nodes below
+    /// (and including) `vtree`
+    fn subtree_range(&self, vtree: usize) -> (usize, usize) {
+        let children = self.vtree_children();
+        let (mut lo, mut hi) = (vtree, vtree);
+        while let Some((l, _)) = children[lo] {
+            lo = l;
+        }
+        while let Some((_, r)) = children[hi] {
+            hi = r;
+        }
+        (lo, hi)
+    }
+
+    fn clear_caches(&mut self, vtree: usize) {
+        self.app_cache[vtree].clear();
+        self.cond_cache[vtree].clear();
+        self.exists_cache[vtree].clear();
+    }
+
+    /// Installs `vtree`, which differs from the current vtree only below the
+    /// node at in-order index `root` (an index of the new vtree), and rewrites
+    /// the SDDs to match. The vtree nodes below `root` are first renumbered by
+    /// `perm`, from old to new in-order index. Then each or-node in `moved`
+    /// (regular pointers, already renumbered), whose elements are no longer a
+    /// valid decomposition, is recomputed as the disjunction of its elements
+    /// in the new vtree. This only creates nodes below `root`. Finally, the
+    /// or-nodes above `root` which refer to a renumbered or recomputed node are
+    /// recompressed with the new pointers, which needs no further `apply`;
+    /// the external references are updated likewise. As with
+    /// `garbage_collect`, any other `SddPtr` is invalid afterwards.
+    fn rewrite_below(&mut self, vtree: VTree, root: usize, perm: &[usize],
+                     moved: &[SddPtr], cleared: &[usize]) {
+        self.ref_table.remove_dead();
+        let relabel = |p: SddPtr| -> SddPtr {
+            if p.is_const() { p } else { p.with_vtree(perm[p.vtree()] as u16) }
+        };
+        let renumbered: Vec<usize> = (0..perm.len()).filter(|&i| perm[i] != i).collect();
+        if !renumbered.is_empty() {
+            // every node between the first and last renumbered ones may refer
+            // to a renumbered node; shifting every node of a subtree by the
+            // same amount keeps the primes of each of its nodes in order
+            self.tbl.permute(perm);
+            for i in renumbered[0]..renumbered[renumbered.len() - 1] + 1 {
+                self.tbl.relabel_sdds(i, &relabel);
+            }
+        }
+        self.parent_ptr = into_parent_ptr_vec(&vtree);
+        self.vtree = vtree;
+        for &v in cleared.iter() {
+            self.clear_caches(v);
+        }
+        let mut ancestor = self.parent_ptr[root].0;
+        while let Some(v) = ancestor {
+            self.clear_caches(v);
+            ancestor = self.parent_ptr[v].0;
+        }
+
+        let mut map: HashMap<SddPtr, SddPtr> = HashMap::new();
+        for &n in moved.iter() {
+            let mut f = SddPtr::new_const(false);
+            for (p, s) in self.elements(n) {
+                let term = self.and(p, s);
+                f = self.or(f, term);
+            }
+            map.insert(n, f);
+        }
+        for &n in moved.iter() {
+            self.tbl.remove_sdds(n.vtree(), &[n]);
+        }
+
+        let fix = |map: &HashMap<SddPtr, SddPtr>, p: SddPtr| -> SddPtr {
+            let p = relabel(p);
+            if p.is_const() || p.is_bdd() {
+                return p;
+            }
+            match map.get(&p.regular()) {
+                Some(r) => if p.is_compl() { r.neg() } else { *r },
+                None => p,
+            }
+        };
+        let mut ancestor = self.parent_ptr[root].0;
+        while let Some(v) = ancestor {
+            let mut changed = Vec::new();
+            for n in self.tbl.live_sdds(v) {
+                let elems = self.elements(n);
+                let new_elems: Vec<(SddPtr, SddPtr)> = elems.iter()
+                    .map(|&(p, s)| (fix(&map, p), fix(&map, s)))
+                    .collect();
+                if new_elems != elems {
+                    changed.push((n, new_elems));
+                }
+            }
+            // the stale nodes go first, so that they cannot alias the new ones
+            let stale: Vec<SddPtr> = changed.iter().map(|&(n, _)| n).collect();
+            self.tbl.remove_sdds(v, &stale);
+            for (n, elems) in changed {
+                let r = self.compress(elems, v);
+                map.insert(n, r);
+            }
+            ancestor = self.parent_ptr[v].0;
+        }
+        self.ref_table.remap(&|p: &SddPtr| fix(&map, *p));
+    }
+
+    /// Rotates `((a, b), c)` at `x` into `(a, (b, c))`; returns the in-order
+    /// index of the new subtree root, where `rotate_left` undoes it, or
+    /// `None` if the rotation does not apply. The nodes at `x` which depend
+    /// on `a` move to the new root; the rest, and the nodes at the old left
+    /// child, are unchanged.
+    fn try_rotate_right(&mut self, x: usize) -> Option<usize> {
+        let t = vtree::rotate_right(&self.vtree, x)?;
+        let w = self.vtree_children()[x].unwrap().0;
+        let moved: Vec<SddPtr> = self.tbl.live_sdds(x).into_iter().filter(|&n| {
+            self.tbl.sdd_slice_or_panic(n).iter().any(|&(p, _)| !p.is_const() && p.vtree() <= w)
+        }).collect();
+        let perm: Vec<usize> = (0..self.parent_ptr.len()).collect();
+        self.rewrite_below(t, w, &perm, &moved, &[w, x]);
+        Some(w)
+    }
+
+    /// Rotates `(a, (b, c))` at `w` into `((a, b), c)`; the inverse of
+    /// `try_rotate_right`. The nodes at `w` which depend on `c` move to the
+    /// new root.
+    fn try_rotate_left(&mut self, w: usize) -> Option<usize> {
+        let t = vtree::rotate_left(&self.vtree, w)?;
+        let x = self.vtree_children()[w].unwrap().1;
+        let moved: Vec<SddPtr> = self.tbl.live_sdds(w).into_iter().filter(|&n| {
+            self.tbl.sdd_slice_or_panic(n).iter().any(|&(_, s)| !s.is_const() && s.vtree() >= x)
+        }).collect();
+        let perm: Vec<usize> = (0..self.parent_ptr.len()).collect();
+        self.rewrite_below(t, x, &perm, &moved, &[w, x]);
+        Some(x)
+    }
+
+    /// Swaps the children of `v`; returns the new in-order index of `v`,
+    /// where a second swap undoes it. Every node at `v` is recomputed, while
+    /// the nodes below `v` are only renumbered.
+    fn try_swap(&mut self, v: usize) -> Option<usize> {
+        let t = vtree::swap(&self.vtree, v)?;
+        let (lo, hi) = self.subtree_range(v);
+        let new_v = lo + hi - v;
+        let perm: Vec<usize> = (0..self.parent_ptr.len()).map(|i| {
+            if i < lo || i > hi {
+                i
+            } else if i < v {
+                i + hi - v + 1
+            } else if i > v {
+                i - (v - lo) - 1
+            } else {
+                new_v
+            }
+        }).collect();
+        let moved: Vec<SddPtr> = self.tbl.live_sdds(v).into_iter()
+            .map(|n| n.with_vtree(new_v as u16))
+            .collect();
+        let cleared: Vec<usize> = (lo..hi + 1).collect();
+        self.rewrite_below(t, new_v, &perm, &moved, &cleared);
+        Some(new_v)
+    }
+
+    /// Rotates the vtree node `vtree` (an in-order index) to the right; see
+    /// `vtree::rotate_right`. Only the or-nodes at `vtree` which depend on
+    /// the left grandchild are recomputed; the nodes above it which refer to
+    /// them are updated in place. SDDs reachable from an external reference
+    /// keep their function, and every other `SddPtr` is invalidated.
+    pub fn rotate_right(&mut self, vtree: usize) {
+        match self.try_rotate_right(vtree) {
+            Some(_) => (),
+            None => panic!("vtree node {} cannot be rotated right", vtree),
+        }
+    }
+
+    /// Rotates the vtree node `vtree` to the left; see `vtree::rotate_left`
+    /// and `rotate_right`
+    pub fn rotate_left(&mut self, vtree: usize) {
+        match self.try_rotate_left(vtree) {
+            Some(_) => (),
+            None => panic!("vtree node {} cannot be rotated left", vtree),
+        }
+    }
+
+    /// Swaps the children of the vtree node `vtree`. The or-nodes at `vtree`
+    /// are recomputed and the nodes below it are renumbered; see
+    /// `rotate_right`.
+    pub fn swap(&mut self, vtree: usize) {
+        match self.try_swap(vtree) {
+            Some(_) => (),
+            None => panic!("vtree node {} is a leaf", vtree),
+        }
+    }
+
+    /// The number of nodes reachable from a live external reference,
+    /// including the nodes of each sub-BDD
+    fn live_size(&self) -> usize {
+        let mut bdd_roots: Vec<Vec<BddPtr>> = self.app_cache.iter().map(|_| Vec::new()).collect();
+        let mut seen = HashSet::new();
+        let mut stack = self.ref_table.roots();
+        while let Some(ptr) = stack.pop() {
+            if ptr.is_const() {
+                continue;
+            } else if ptr.is_bdd() {
+                bdd_roots[ptr.vtree()].push(ptr.as_bdd_ptr());
+            } else if seen.insert(ptr.regular()) {
+                for &(p, s) in self.tbl.sdd_slice_or_panic(ptr).iter() {
+                    stack.push(p);
+                    stack.push(s);
+                }
+            }
+        }
+        let mut cnt = seen.len();
+        for (idx, roots) in bdd_roots.iter().enumerate() {
+            if !roots.is_empty() {
+                cnt += self.tbl.bdd_man(idx).num_reachable(roots);
+            }
+        }
+        cnt
+    }
+
+    /// Local search for a smaller vtree. Each pass visits every vtree node
+    /// and tries a right rotation, a left rotation and a swap there in turn;
+    /// a move is kept if it reduces the number of nodes reachable from the
+    /// external references, and is otherwise undone by its inverse. The
+    /// search stops after a pass without improvement, or after
+    /// `MINIMIZE_PASSES` passes.
+    ///
+    /// The search garbage collects and rewrites the SDD table: SDDs reachable
+    /// from an external reference keep their function and are read back with
+    /// `get_ext`, and every other `SddPtr` is invalid after this call.
+    pub fn minimize(&mut self) {
+        self.garbage_collect();
+        let mut size = self.live_size();
+        for _ in 0..MINIMIZE_PASSES {
+            let mut improved = false;
+            for idx in 0..self.parent_ptr.len() {
+                if let Some(w) = self.try_rotate_right(idx) {
+                    let s = self.live_size();
+                    if s < size {
+                        size = s;
+                        improved = true;
+                        continue;
+                    }
+                    self.try_rotate_left(w);
+                }
+                if let Some(x) = self.try_rotate_left(idx) {
+                    let s = self.live_size();
+                    if s < size {
+                        size = s;
+                        improved = true;
+                        continue;
+                    }
+                    self.try_rotate_right(x);
+                }
+                if let Some(v) = self.try_swap(idx) {
+                    let s = self.live_size();
+                    if s < size {
+                        size = s;
+                        improved = true;
+                        continue;
+                    }
+                    self.try_swap(v);
+                }
+            }
+            // reclaim the nodes left behind by the undone moves
+            self.garbage_collect();
+            if !improved {
+                break;
+            }
+        }
+    }
+
+    /// Enables automatic minimization: once the number of allocated nodes
+    /// exceeds `threshold`, the next call to `minimize_if_needed` searches
+    /// for a smaller vtree. `None` disables automatic minimization.
+    pub fn set_minimize_threshold(&mut self, threshold: Option<usize>) {
+        self.minimize_threshold = threshold;
+    }
+
+    /// Runs `minimize` if automatic minimization is enabled and the node
+    /// count has exceeded the threshold, after which the threshold is raised
+    /// to twice the minimized size. Returns true if it ran, in which case, as
+    /// after `minimize`, only the SDDs held by an external reference are
+    /// still valid. No other operation calls it.
+    pub fn minimize_if_needed(&mut self) -> bool {
+        match self.minimize_threshold {
+            Some(t) if self.num_nodes() > t => {
+                self.minimize();
+                self.minimize_threshold = Some(cmp::max(t, 2 * self.num_nodes()));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Total number of allocated nodes, including the nodes of each sub-BDD
+    pub fn num_nodes(&self) -> usize {
+        self.tbl.num_nodes()
+    }
+
     pub fn negate(&self, a: SddPtr) -> SddPtr {
         a.neg()
     }
@@ -185,7 +906,7 @@ impl SddManager {
             let (cur_p, cur_s) = node[i];
             if s == cur_s {
                 // disjoin the prime
-                p = self.or_internal(p, cur_p);
+                p = self.or(p, cur_p);
             } else {
                 // found a unique sub, start a new chain
                 r.push((p, s));
@@ -220,7 +941,13 @@ impl SddManager {
                 return p2;
             }
         }
-        if r[0].1.is_compl() {
+        // a sub-BDD carries its complement bit in the BDD pointer
+        let first_compl = if r[0].1.is_bdd() {
+            r[0].1.as_bdd_ptr().is_compl()
+        } else {
+            r[0].1.is_compl()
+        };
+        if first_compl {
             // guarantee first sub in the first node is not complemented
             // (regular form)
             let compl_r = r.iter().map(|&(ref p, ref s)| (*p, s.neg())).collect();
@@ -232,51 +959,55 @@ impl SddManager {
         }
     }
 
-    pub fn or_internal(&mut self, a: SddPtr, b: SddPtr) -> SddPtr {
-        self.and_rec(a.neg(), b.neg()).neg()
-    }
-
-
-    fn and_rec(&mut self, a: SddPtr, b: SddPtr) -> SddPtr {
+    /// Applies the Boolean operator `op` to `a` and `b`
+    pub fn apply(&mut self, op: BoolOp, a: SddPtr, b: SddPtr) -> SddPtr {
         // println!("applying\n {}\n {}\n",
         //          self.print_sdd_internal(a), self.print_sdd_internal(b));
 
         // first, check for a base case
-        match (a, b) {
-            (a, b) if a.is_true() => return b,
-            (a, b) if b.is_true() => return a,
-            (a, _) if a.is_false() => return SddPtr::new_const(false),
-            (_, b) if b.is_false() => return SddPtr::new_const(false),
-            (a, b) if self.sdd_eq(a, b) => return a,
-            (a, b) if self.sdd_eq(a, b.neg()) => return SddPtr::new_const(false),
-            _ => (),
-        };
+        if !op.depends_on_second() {
+            return apply_unary(op.fix_second(false), a);
+        }
+        if !op.depends_on_first() {
+            return apply_unary(op.fix_first(false), b);
+        }
+        if a.is_const() {
+            return apply_unary(op.fix_first(a.is_true()), b);
+        }
+        if b.is_const() {
+            return apply_unary(op.fix_second(b.is_true()), a);
+        }
+        if self.sdd_eq(a, b) {
+            return apply_unary(op.diag(), a);
+        }
+        if self.sdd_eq(a, b.neg()) {
+            return apply_unary(op.anti_diag(), a);
+        }
 
-        // normalize the pointers to increase cache hit rate
-        let (a, b) = if a < b { (a, b) } else { (b, a) };
         // check if both are BDDs; if they are, just invoke their apply
         // functions
         if a.is_bdd() && b.is_bdd() && a.vtree() == b.vtree() {
             // both nodes are BDDs, so simply apply them together
             // and return the result
-            let a_bdd = a.as_bdd_ptr();
-            let b_bdd = b.as_bdd_ptr();
-            let r = self.tbl.bdd_man_mut(a.vtree()).and(a_bdd, b_bdd);
-            return if r.is_false() {
-                SddPtr::new_const(false)
-            } else if r.is_true() {
-                SddPtr::new_const(true)
-            } else {
-                SddPtr::new_bdd(r, a.vtree() as u16)
-            };
+            let r = self.tbl
+                .bdd_man_mut(a.vtree())
+                .apply(op, a.as_bdd_ptr(), b.as_bdd_ptr());
+            return self.wrap_bdd(r, a.vtree());
         }
 
+        // normalize the application to increase cache hit rate: complemented
+        // or-nodes are folded into the operator, and the operator is made false
+        // on (F, F) by complementing the result
+        let (a, op) = if a.is_compl() { (a.neg(), op.neg_first()) } else { (a, op) };
+        let (b, op) = if b.is_compl() { (b.neg(), op.neg_second()) } else { (b, op) };
+        let (op, compl) = if op.eval(false, false) { (op.neg(), true) } else { (op, false) };
         // normalize so `a` is always prime if possible
-        let (a, b) = if a.vtree() == b.vtree() ||
-            is_prime(&self.vtree, a.vtree(), b.vtree()) {
-            (a, b)
+        let (a, b, op) = if a.vtree() == b.vtree() {
+            if a < b { (a, b, op) } else { (b, a, op.swap()) }
+        } else if is_prime(&self.vtree, a.vtree(), b.vtree()) {
+            (a, b, op)
         } else {
-            (b, a)
+            (b, a, op.swap())
         };
 
         let av = a.vtree();
@@ -284,9 +1015,8 @@ impl SddManager {
         let lca = least_common_ancestor(&self.parent_ptr, av, bv);
 
         // check if we have this application cached
-        let c = self.app_cache[lca].get((a, b));
-        if c.is_some() {
-            return c.unwrap();
+        if let Some(r) = self.app_cache[lca].get((op.table(), a, b)) {
+            return if compl { r.neg() } else { r };
         }
 
         // now we determine the current iterator for primes and subs
@@ -305,33 +1035,22 @@ impl SddManager {
             (outer, inner)
         } else if lca == av {
             let outer = self.tbl.sdd_slice_or_panic(a);
-            inner_v = vec![(SddPtr::new_const(true), b.regular())];
+            inner_v = vec![(SddPtr::new_const(true), b)];
             (outer, inner_v.as_slice())
         } else if lca == bv {
             // the sub must always be true
-            let v = if a.is_compl() {
-                SddPtr::new_const(false)
-            } else {
-                SddPtr::new_const(true)
-            };
-            outer_v = vec![(a, v), (a.neg(), v.neg())];
+            outer_v = vec![(a, SddPtr::new_const(true)), (a.neg(), SddPtr::new_const(false))];
             let inner = self.tbl.sdd_slice_or_panic(b);
             (outer_v.as_slice(), inner)
         } else {
-            // avoid double-negations by checking to see if `a` is already
-            // negated
-            let v = if a.is_compl() {
-                SddPtr::new_const(false)
-            } else {
-                SddPtr::new_const(true)
-            };
-            outer_v = vec![(a, v), (a.neg(), v.neg())];
-            inner_v = vec![(SddPtr::new_const(true), b.regular())];
+            outer_v = vec![(a, SddPtr::new_const(true)), (a.neg(), SddPtr::new_const(false))];
+            inner_v = vec![(SddPtr::new_const(true), b)];
             (outer_v.as_slice(), inner_v.as_slice())
         };
 
 
-        // iterate over each prime/sum pair and do the relevant application
+        // iterate over each prime/sum pair and do the relevant application:
+        // the primes are conjoined and the operator is applied to the subs
         // TODO: Optimize this by avoiding unnecessary conjunctions of primes
         //       specifically, from the SDD library:
         // if p1i = p2j, then p1k x p2j = false for all k<>i
@@ -339,21 +1058,19 @@ impl SddManager {
         // if p1i*p2j=p2j, then pik x p2j = false for all k<>i
         let mut r: Vec<(SddPtr, SddPtr)> = Vec::with_capacity(30);
         for &(ref p1, ref s1) in outer.iter() {
-            let s1 = if a.is_compl() { s1.neg() } else { *s1 };
             for &(ref p2, ref s2) in inner.iter() {
-                let s2 = if b.is_compl() { s2.neg() } else { *s2 };
-                let p = self.and_rec(*p1, *p2);
+                let p = self.apply(BoolOp::And, *p1, *p2);
                 if p.is_false() {
                     continue;
                 }
 
-                let s = self.and_rec(s1, s2);
+                let s = self.apply(op, *s1, *s2);
                 // check if one of the nodes is true; if it is, we can
                 // return a `true` SddPtr here
                 if p.is_true() && s.is_true() {
                     let new_v = SddPtr::new_const(true);
-                    self.app_cache[lca].insert((a, b), new_v.clone());
-                    return new_v;
+                    self.app_cache[lca].insert((op.table(), a, b), new_v);
+                    return if compl { new_v.neg() } else { new_v };
                 }
 
                 r.push((p, s));
@@ -362,16 +1079,471 @@ impl SddManager {
 
         // canonicalize
         let ptr = self.compress(r, lca);
-        self.app_cache[lca].insert((a, b), ptr);
-        ptr
+        self.app_cache[lca].insert((op.table(), a, b), ptr);
+        if compl { ptr.neg() } else { ptr }
     }
 
     pub fn and(&mut self, a: SddPtr, b: SddPtr) -> SddPtr {
-        self.and_rec(a, b)
+        self.apply(BoolOp::And, a, b)
     }
 
     pub fn or(&mut self, a: SddPtr, b: SddPtr) -> SddPtr {
-        self.or_internal(a, b)
+        self.apply(BoolOp::Or, a, b)
+    }
+
+    pub fn xor(&mut self, a: SddPtr, b: SddPtr) -> SddPtr {
+        self.apply(BoolOp::Xor, a, b)
+    }
+
+    pub fn iff(&mut self, a: SddPtr, b: SddPtr) -> SddPtr {
+        self.apply(BoolOp::Iff, a, b)
+    }
+
+    /// Converts the result of an operation on the BDD at vtree leaf `vtree`
+    /// back into an SDD pointer
+    fn wrap_bdd(&self, ptr: BddPtr, vtree: usize) -> SddPtr {
+        if ptr.is_false() {
+            SddPtr::new_const(false)
+        } else if ptr.is_true() {
+            SddPtr::new_const(true)
+        } else {
+            SddPtr::new_bdd(ptr, vtree as u16)
+        }
+    }
+
+    /// Returns a vector indexed by vtree node which is true if the subtree
+    /// rooted at that node contains any of the variables in `vars`
+    fn vtree_contains(&self, vars: &[VarLabel]) -> Vec<bool> {
+        let mut r = vec![false; self.parent_ptr.len()];
+        for v in vars.iter() {
+            let mut cur = self.vtree.find_leaf_idx(&|l| l.contains(v));
+            while let Some(idx) = cur {
+                r[idx] = true;
+                cur = self.parent_ptr[idx].0;
+            }
+        }
+        r
+    }
+
+    /// the elements of the or-node `ptr`, with the subs negated if `ptr` is
+    /// complemented
+    fn elements(&self, ptr: SddPtr) -> Vec<(SddPtr, SddPtr)> {
+        self.tbl
+            .sdd_slice_or_panic(ptr)
+            .iter()
+            .map(|&(p, s)| (p, if ptr.is_compl() { s.neg() } else { s }))
+            .collect()
+    }
+
+    fn condition_helper(&mut self, ptr: SddPtr, lit: Literal, relevant: &[bool]) -> SddPtr {
+        if ptr.is_const() || !relevant[ptr.vtree()] {
+            return ptr;
+        }
+        if let Some(r) = self.cond_cache[ptr.vtree()].get((ptr, lit)) {
+            return r;
+        }
+        let res = if ptr.is_bdd() {
+            let bdd_lbl = self.tbl.sdd_to_bdd[&lit.get_label()];
+            let r = self.tbl
+                .bdd_man_mut(ptr.vtree())
+                .condition(ptr.as_bdd_ptr(), bdd_lbl, lit.get_polarity());
+            self.wrap_bdd(r, ptr.vtree())
+        } else {
+            // the conditioned primes still partition the remaining variables,
+            // so the node only needs to be re-canonicalized
+            let mut node = Vec::new();
+            for (p, s) in self.elements(ptr) {
+                let p_c = self.condition_helper(p, lit, relevant);
+                if p_c.is_false() {
+                    continue;
+                }
+                let s_c = self.condition_helper(s, lit, relevant);
+                node.push((p_c, s_c));
+            }
+            self.compress(node, ptr.vtree())
+        };
+        self.cond_cache[ptr.vtree()].insert((ptr, lit), res);
+        res
+    }
+
+    /// Computes `ptr | lbl = value`
+    pub fn condition(&mut self, ptr: SddPtr, lbl: VarLabel, value: bool) -> SddPtr {
+        let relevant = self.vtree_contains(&[lbl]);
+        self.condition_helper(ptr, Literal::new(lbl, value), &relevant)
+    }
+
+    fn exists_helper(&mut self, ptr: SddPtr, lbl: VarLabel, leaf: usize,
+                     relevant: &[bool]) -> SddPtr {
+        if ptr.is_const() || !relevant[ptr.vtree()] {
+            return ptr;
+        }
+        if let Some(r) = self.exists_cache[ptr.vtree()].get((ptr, lbl)) {
+            return r;
+        }
+        let res = if ptr.is_bdd() {
+            let bdd_lbl = self.tbl.sdd_to_bdd[&lbl];
+            let r = self.tbl.bdd_man_mut(ptr.vtree()).exists(ptr.as_bdd_ptr(), bdd_lbl);
+            self.wrap_bdd(r, ptr.vtree())
+        } else if leaf > ptr.vtree() {
+            // `lbl` is in the subs, so the primes still form a partition
+            let mut node = Vec::new();
+            for (p, s) in self.elements(ptr) {
+                let s_q = self.exists_helper(s, lbl, leaf, relevant);
+                node.push((p, s_q));
+            }
+            self.compress(node, ptr.vtree())
+        } else {
+            // `lbl` is in the primes, which may overlap once it is quantified,
+            // so the elements are disjoined
+            let mut r = SddPtr::new_const(false);
+            for (p, s) in self.elements(ptr) {
+                let p_q = self.exists_helper(p, lbl, leaf, relevant);
+                let term = self.and(p_q, s);
+                r = self.or(r, term);
+            }
+            r
+        };
+        self.exists_cache[ptr.vtree()].insert((ptr, lbl), res);
+        res
+    }
+
+    /// Existentially quantifies `lbl` out of `ptr`
+    pub fn exists(&mut self, ptr: SddPtr, lbl: VarLabel) -> SddPtr {
+        let leaf = match self.vtree.find_leaf_idx(&|l| l.contains(&lbl)) {
+            None => panic!("var {:?} not found", lbl),
+            Some(a) => a,
+        };
+        let relevant = self.vtree_contains(&[lbl]);
+        self.exists_helper(ptr, lbl, leaf, &relevant)
+    }
+
+    fn exists_set_helper(&mut self, ptr: SddPtr, vars: &[VarLabel],
+                         relevant: &[bool],
+                         seen: &mut HashMap<SddPtr, SddPtr>) -> SddPtr {
+        if ptr.is_const() || !relevant[ptr.vtree()] {
+            return ptr;
+        }
+        if let Some(r) = seen.get(&ptr) {
+            return *r;
+        }
+        let res = if ptr.is_bdd() {
+            // quantify the variables which live in this vtree leaf
+            let bdd_vars: Vec<VarLabel> = vars.iter()
+                .filter_map(|v| {
+                    let bdd_v = self.tbl.sdd_to_bdd.get(v).unwrap();
+                    if self.tbl.bdd_conv(ptr.vtree()).get(bdd_v) == Some(v) {
+                        Some(*bdd_v)
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            let r = self.tbl.bdd_man_mut(ptr.vtree()).exists_set(ptr.as_bdd_ptr(), &bdd_vars);
+            self.wrap_bdd(r, ptr.vtree())
+        } else {
+            // quantification distributes over the disjunction, and over the
+            // conjunction of each prime and sub since they share no variables
+            let mut r = SddPtr::new_const(false);
+            for &(p, s) in self.tbl.sdd_slice_or_panic(ptr).iter() {
+                let s = if ptr.is_compl() { s.neg() } else { s };
+                let p_q = self.exists_set_helper(p, vars, relevant, seen);
+                let s_q = self.exists_set_helper(s, vars, relevant, seen);
+                let term = self.and(p_q, s_q);
+                r = self.or(r, term);
+            }
+            r
+        };
+        seen.insert(ptr, res);
+        res
+    }
+
+    /// Existentially quantifies out every variable in `vars` from `ptr`
+    pub fn exists_set(&mut self, ptr: SddPtr, vars: &[VarLabel]) -> SddPtr {
+        let relevant = self.vtree_contains(vars);
+        self.exists_set_helper(ptr, vars, &relevant, &mut HashMap::new())
+    }
+
+    /// Universally quantifies out every variable in `vars` from `ptr`
+    pub fn forall(&mut self, ptr: SddPtr, vars: &[VarLabel]) -> SddPtr {
+        self.exists_set(ptr.neg(), vars).neg()
+    }
+
+    /// The pair `(left, right)` of children of each internal vtree node
+    fn vtree_children(&self) -> Vec<Option<(usize, usize)>> {
+        let mut children: Vec<(Option<usize>, Option<usize>)> =
+            self.parent_ptr.iter().map(|_| (None, None)).collect();
+        for (idx, &(parent, _)) in self.parent_ptr.iter().enumerate() {
+            match parent {
+                Some(p) if idx < p => children[p].0 = Some(idx),
+                Some(p) => children[p].1 = Some(idx),
+                None => (),
+            }
+        }
+        children
+            .into_iter()
+            .map(|c| match c {
+                (Some(l), Some(r)) => Some((l, r)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Weighted model count of `ptr` over the variables of the vtree node
+    /// `vtree`, which must contain the vtree node of `ptr`. `smooth[v]` is the
+    /// weighted model count of true over the variables of `v`.
+    fn wmc_smoothed<S: Semiring>(
+        &self,
+        ptr: SddPtr,
+        vtree: usize,
+        leaf_weights: &LeafWeights<S>,
+        smooth: &Vec<S>,
+        children: &Vec<Option<(usize, usize)>>,
+        compute_table: &mut HashMap<SddPtr, S>,
+    ) -> S {
+        if ptr.is_true() {
+            return smooth[vtree].clone();
+        } else if ptr.is_false() {
+            return S::zero();
+        }
+        let mut v = self.wmc_helper(ptr, leaf_weights, smooth, children, compute_table);
+        // smooth over every vtree node which is a sibling of a node on the
+        // path from `ptr` up to `vtree`
+        let mut cur = ptr.vtree();
+        while cur != vtree {
+            let parent = self.parent_ptr[cur].0.unwrap();
+            let (l, r) = children[parent].unwrap();
+            let sibling = if l == cur { r } else { l };
+            v = v.product(&smooth[sibling]);
+            cur = parent;
+        }
+        v
+    }
+
+    /// Weighted model count of the non-constant `ptr` over the variables of
+    /// its own vtree node
+    fn wmc_helper<S: Semiring>(
+        &self,
+        ptr: SddPtr,
+        leaf_weights: &LeafWeights<S>,
+        smooth: &Vec<S>,
+        children: &Vec<Option<(usize, usize)>>,
+        compute_table: &mut HashMap<SddPtr, S>,
+    ) -> S {
+        if let Some(a) = compute_table.get(&ptr) {
+            return a.clone();
+        }
+        let res = if ptr.is_bdd() {
+            let leaf = ptr.vtree();
+            self.tbl.bdd_man(leaf).wmc_semiring(ptr.as_bdd_ptr(), &leaf_weights[&leaf])
+        } else {
+            // the primes are mutually exclusive, so the count is the sum of
+            // the count of each element; a complemented node negates its subs
+            let (l, r) = children[ptr.vtree()].unwrap();
+            let mut res = S::zero();
+            for (p, s) in self.tbl.sdd_slice_or_panic(ptr).iter() {
+                let s = if ptr.is_compl() { s.neg() } else { *s };
+                let p_v = self.wmc_smoothed(*p, l, leaf_weights, smooth, children, compute_table);
+                let s_v = self.wmc_smoothed(s, r, leaf_weights, smooth, children, compute_table);
+                res = res.sum(&p_v.product(&s_v));
+            }
+            res
+        };
+        compute_table.insert(ptr, res.clone());
+        res
+    }
+
+    /// Weighted model count of `ptr` in the semiring `S` over every variable
+    /// in the vtree, where `weights` maps every variable to its `(low, high)`
+    /// weight. Each vtree leaf is counted by its BDD manager.
+    pub fn wmc_semiring<S: Semiring>(&self, ptr: SddPtr, weights: &HashMap<VarLabel, (S, S)>) -> S {
+        let (leaf_weights, smooth, children) = self.wmc_setup(weights);
+        let root = self.parent_ptr.iter().position(|&(p, _)| p.is_none()).unwrap();
+        self.wmc_smoothed(ptr, root, &leaf_weights, &smooth, &children, &mut HashMap::new())
+    }
+
+    /// Translates `weights` into the labels of each leaf's BDD manager, and
+    /// computes the smoothing factor of each vtree node bottom-up
+    fn wmc_setup<S: Semiring>(&self, weights: &HashMap<VarLabel, (S, S)>)
+                              -> (LeafWeights<S>, Vec<S>, VtreeChildren) {
+        let children = self.vtree_children();
+        let mut leaf_weights = HashMap::new();
+        let mut smooth: Vec<S> = self.parent_ptr.iter().map(|_| S::one()).collect();
+        let mut by_height: Vec<usize> = (0..self.parent_ptr.len()).collect();
+        by_height.sort_by_key(|&idx| cmp::Reverse(self.parent_ptr[idx].1));
+        for &idx in by_height.iter() {
+            match children[idx] {
+                Some((l, r)) => smooth[idx] = smooth[l].product(&smooth[r]),
+                None => {
+                    let mut leaf_w = HashMap::new();
+                    for (bdd_lbl, sdd_lbl) in self.tbl.bdd_conv(idx).iter() {
+                        let (low, high) = &weights[sdd_lbl];
+                        leaf_w.insert(*bdd_lbl, (low.clone(), high.clone()));
+                        smooth[idx] = smooth[idx].product(&low.sum(high));
+                    }
+                    leaf_weights.insert(idx, leaf_w);
+                }
+            }
+        }
+        (leaf_weights, smooth, children)
+    }
+
+    /// Weighted model count of `ptr` over every variable in the vtree; agrees
+    /// with `BddManager::wmc` on the same function
+    pub fn wmc<T: Num + Clone + Debug + Copy>(&self, ptr: SddPtr, params: &BddWmc<T>) -> T {
+        self.wmc_semiring(ptr, &params.semiring_weights()).0
+    }
+
+    /// An endless iterator of independent samples from the models of `ptr`
+    /// over every variable in the vtree, each drawn with probability
+    /// proportional to its weight under `params`. The counts of every node are
+    /// computed once up front, after which each sample takes a single pass
+    /// down the SDD. Yields nothing if `ptr` is unsatisfiable.
+    pub fn sample<'a, R: Rng>(&'a self, ptr: SddPtr, params: &BddWmc<f64>,
+                              rng: &'a mut R) -> SddSampleIter<'a, R> {
+        let weights = params.semiring_weights();
+        let (leaf_weights, smooth, children) = self.wmc_setup(&weights);
+        let root = self.parent_ptr.iter().position(|&(p, _)| p.is_none()).unwrap();
+        let mut counts = HashMap::new();
+        self.wmc_smoothed(ptr, root, &leaf_weights, &smooth, &children, &mut counts);
+
+        // the BDD nodes reachable from `ptr`, grouped by vtree leaf
+        let mut leaf_roots: HashMap<usize, Vec<BddPtr>> = HashMap::new();
+        for n in counts.keys().filter(|n| n.is_bdd()) {
+            leaf_roots.entry(n.vtree()).or_default().push(n.as_bdd_ptr());
+        }
+        let leaf_tables = leaf_roots
+            .iter()
+            .map(|(&leaf, roots)| {
+                let w = leaf_weights[&leaf].iter().map(|(k, &(l, h))| (*k, (l.0, h.0))).collect();
+                (leaf, self.tbl.bdd_man(leaf).sample_table(roots, &w))
+            })
+            .collect();
+
+        let mut vars: Vec<Vec<VarLabel>> = self.parent_ptr.iter().map(|_| Vec::new()).collect();
+        let mut by_height: Vec<usize> = (0..self.parent_ptr.len()).collect();
+        by_height.sort_by_key(|&idx| cmp::Reverse(self.parent_ptr[idx].1));
+        for &idx in by_height.iter() {
+            vars[idx] = match children[idx] {
+                Some((l, r)) => vars[l].iter().chain(vars[r].iter()).cloned().collect(),
+                None => self.tbl.bdd_conv(idx).values().cloned().collect(),
+            };
+        }
+        SddSampleIter {
+            man: self,
+            ptr,
+            root,
+            weights: weights.iter().map(|(k, &(l, h))| (*k, (l.0, h.0))).collect(),
+            leaf_weights,
+            smooth,
+            children,
+            counts,
+            leaf_tables,
+            vars,
+            rng,
+        }
+    }
+
+    /// Samples the models of `ptr` uniformly at random; see `sample`
+    pub fn sample_uniform<'a, R: Rng>(&'a self, ptr: SddPtr,
+                                      rng: &'a mut R) -> SddSampleIter<'a, R> {
+        let weights = self.tbl.sdd_to_bdd.keys().map(|v| (*v, (1.0, 1.0))).collect();
+        self.sample(ptr, &BddWmc::new_with_default(0.0, 1.0, weights), rng)
+    }
+
+    /// Model count of the non-constant `ptr` over the counted variables of
+    /// its own vtree node. `num_vars[v]` is the number of counted variables
+    /// in vtree node `v`, and `leaf_vars[v]` holds the counted local BDD
+    /// labels of the leaf `v`.
+    fn model_count_helper(&self, ptr: SddPtr, num_vars: &Vec<usize>,
+                          leaf_vars: &Vec<Vec<VarLabel>>,
+                          children: &Vec<Option<(usize, usize)>>,
+                          compute_table: &mut HashMap<SddPtr, BigUint>) -> BigUint {
+        if let Some(a) = compute_table.get(&ptr) {
+            return a.clone();
+        }
+        let res = if ptr.is_bdd() {
+            let idx = ptr.vtree();
+            let man = self.tbl.bdd_man(idx);
+            if leaf_vars[idx].len() == self.tbl.bdd_conv(idx).len() {
+                man.model_count(ptr.as_bdd_ptr())
+            } else {
+                man.model_count_vars(ptr.as_bdd_ptr(), &leaf_vars[idx]).unwrap()
+            }
+        } else {
+            let (l, r) = children[ptr.vtree()].unwrap();
+            let mut res = BigUint::zero();
+            for (p, s) in self.tbl.sdd_slice_or_panic(ptr).iter() {
+                let s = if ptr.is_compl() { s.neg() } else { *s };
+                if s.is_false() {
+                    continue;
+                }
+                let p_v = self.model_count_smoothed(*p, l, num_vars, leaf_vars, children, compute_table);
+                let s_v = self.model_count_smoothed(s, r, num_vars, leaf_vars, children, compute_table);
+                res += p_v * s_v;
+            }
+            res
+        };
+        compute_table.insert(ptr, res.clone());
+        res
+    }
+
+    /// Model count of `ptr` over the counted variables of the vtree node
+    /// `vtree`, which must contain the vtree node of `ptr`
+    fn model_count_smoothed(&self, ptr: SddPtr, vtree: usize, num_vars: &Vec<usize>,
+                            leaf_vars: &Vec<Vec<VarLabel>>,
+                            children: &Vec<Option<(usize, usize)>>,
+                            compute_table: &mut HashMap<SddPtr, BigUint>) -> BigUint {
+        if ptr.is_true() {
+            BigUint::one() << num_vars[vtree]
+        } else if ptr.is_false() {
+            BigUint::zero()
+        } else {
+            let v = self.model_count_helper(ptr, num_vars, leaf_vars, children, compute_table);
+            v << (num_vars[vtree] - num_vars[ptr.vtree()])
+        }
+    }
+
+    /// Counts the models of `ptr` over the variables for which `counted`
+    /// holds; `ptr` must not depend on any other variable
+    fn model_count_over<F: Fn(&VarLabel) -> bool>(&self, ptr: SddPtr, counted: F) -> BigUint {
+        let children = self.vtree_children();
+        let mut num_vars: Vec<usize> = self.parent_ptr.iter().map(|_| 0).collect();
+        let mut leaf_vars: Vec<Vec<VarLabel>> = self.parent_ptr.iter().map(|_| Vec::new()).collect();
+        let mut by_height: Vec<usize> = (0..self.parent_ptr.len()).collect();
+        by_height.sort_by_key(|&idx| cmp::Reverse(self.parent_ptr[idx].1));
+        for &idx in by_height.iter() {
+            num_vars[idx] = match children[idx] {
+                Some((l, r)) => num_vars[l] + num_vars[r],
+                None => {
+                    leaf_vars[idx] = self.tbl.bdd_conv(idx).iter()
+                        .filter(|&(_, v)| counted(v))
+                        .map(|(l, _)| *l)
+                        .collect();
+                    leaf_vars[idx].len()
+                }
+            };
+        }
+        let root = self.parent_ptr.iter().position(|&(p, _)| p.is_none()).unwrap();
+        self.model_count_smoothed(ptr, root, &num_vars, &leaf_vars, &children, &mut HashMap::new())
+    }
+
+    /// Counts the models of `ptr` over every variable in the vtree
+    pub fn model_count(&self, ptr: SddPtr) -> BigUint {
+        self.model_count_over(ptr, |_| true)
+    }
+
+    /// Counts the models of `ptr` over the variables in `vars`; duplicates
+    /// are ignored. Returns `None` if a label is not in the vtree or if
+    /// `ptr` depends on a variable outside `vars`.
+    pub fn model_count_vars(&self, ptr: SddPtr, vars: &[VarLabel]) -> Option<BigUint> {
+        if vars.iter().any(|v| !self.tbl.sdd_to_bdd.contains_key(v)) {
+            return None;
+        }
+        let in_set: HashSet<VarLabel> = vars.iter().cloned().collect();
+        if self.support(ptr).iter().any(|v| !in_set.contains(v)) {
+            return None;
+        }
+        Some(self.model_count_over(ptr, |v| in_set.contains(v)))
     }
 
     fn print_sdd_internal(&self, ptr: SddPtr) -> String {
@@ -417,6 +1589,214 @@ impl SddManager {
         self.print_sdd_internal(ptr)
     }
 
+    /// Appends the node for `ptr` and its descendants to `lines`, in the
+    /// `.sdd` format, and returns its id. The id of each node is its line
+    /// number. `offsets` gives the index in the expanded vtree (see
+    /// `vtree::expand_leaves`) of the first node of each vtree node.
+    fn write_sdd_node(&self, ptr: SddPtr, offsets: &[usize], ids: &mut HashMap<SddPtr, usize>,
+                      lits: &mut HashMap<Literal, usize>, lines: &mut Vec<String>) -> usize {
+        if let Some(id) = ids.get(&ptr) {
+            return *id;
+        }
+        let line = if ptr.is_const() {
+            format!("{} {}", if ptr.is_true() { "T" } else { "F" }, lines.len())
+        } else if ptr.is_bdd() {
+            // a BDD node on the `i`th variable of its leaf is a decision on
+            // that variable, normalized for the `i`th node of the expanded leaf
+            let bdd = ptr.as_bdd_ptr();
+            let i = bdd.label().value() as usize;
+            let lbl = self.tbl.bdd_conv(ptr.vtree())[&bdd.label()];
+            let (low, high) = self.tbl.bdd_man(ptr.vtree()).top_cofactors(bdd, bdd.label());
+            let leaf = offsets[ptr.vtree()] + 2 * i;
+            if low.is_const() && high.is_const() {
+                return write_lit(Literal::new(lbl, high.is_true()), leaf, lits, lines);
+            }
+            let high = self.write_sdd_node(self.wrap_bdd(high, ptr.vtree()), offsets, ids, lits, lines);
+            let low = self.write_sdd_node(self.wrap_bdd(low, ptr.vtree()), offsets, ids, lits, lines);
+            let pos = write_lit(Literal::new(lbl, true), leaf, lits, lines);
+            let neg = write_lit(Literal::new(lbl, false), leaf, lits, lines);
+            format!("D {} {} 2 {} {} {} {}", lines.len(), leaf + 1, pos, high, neg, low)
+        } else {
+            let mut elems = Vec::new();
+            for (p, s) in self.elements(ptr) {
+                let p = self.write_sdd_node(p, offsets, ids, lits, lines);
+                let s = self.write_sdd_node(s, offsets, ids, lits, lines);
+                elems.push(format!("{} {}", p, s));
+            }
+            format!("D {} {} {} {}", lines.len(), offsets[ptr.vtree()], elems.len(), elems.join(" "))
+        };
+        let id = lines.len();
+        lines.push(line);
+        ids.insert(ptr, id);
+        id
+    }
+
+    /// Writes `ptr` in the `.sdd` format of the SDD package. Its vtree ids
+    /// refer to `vtree::to_vtree_file(self.get_vtree())`. Since the format has
+    /// neither complemented edges nor sub-BDDs, a negated node is written as
+    /// a separate node, and each sub-BDD as a decision node for every BDD
+    /// node.
+    pub fn to_sdd_file(&self, ptr: SddPtr) -> String {
+        // the expanded vtree replaces a leaf with `n` variables by `2n - 1`
+        // nodes
+        let mut offsets = Vec::new();
+        let mut cur = 0;
+        for n in self.vtree.in_order_iter() {
+            offsets.push(cur);
+            cur += match *n {
+                BTree::Leaf(ref vars) => 2 * vars.len() - 1,
+                BTree::Node(_, _, _) => 1,
+            };
+        }
+        let mut lines = Vec::new();
+        self.write_sdd_node(ptr, &offsets, &mut HashMap::new(), &mut HashMap::new(), &mut lines);
+        let mut r = String::from(SDD_FILE_HEADER);
+        r.push_str(&format!("sdd {}\n", lines.len()));
+        for l in lines.iter() {
+            r.push_str(l);
+            r.push('\n');
+        }
+        r
+    }
+
+    /// Parses an SDD in the `.sdd` format of the SDD package and builds it in
+    /// this manager, which must contain each of its variables; the last node
+    /// in the file is the root. The vtree ids in the file are ignored, so the
+    /// vtree of this manager may differ from the one the file was written
+    /// for. Panics if `s` is malformed.
+    pub fn load_sdd_file(&mut self, s: &str) -> SddPtr {
+        let mut nodes: HashMap<usize, SddPtr> = HashMap::new();
+        let mut root = None;
+        for line in s.lines() {
+            let tok: Vec<&str> = line.split_whitespace().collect();
+            if tok.is_empty() || tok[0] == "c" || tok[0] == "sdd" {
+                continue;
+            }
+            let num = |i: usize| -> i64 {
+                match tok.get(i).and_then(|t| t.parse().ok()) {
+                    Some(n) => n,
+                    None => panic!("invalid sdd line: {}", line),
+                }
+            };
+            let node = |id: i64| -> SddPtr {
+                match nodes.get(&(id as usize)) {
+                    Some(n) => *n,
+                    None => panic!("undefined sdd node {} in line: {}", id, line),
+                }
+            };
+            let ptr = match tok[0] {
+                "F" => SddPtr::new_const(false),
+                "T" => SddPtr::new_const(true),
+                "L" => {
+                    let lit = num(3);
+                    assert!(lit != 0, "variables are numbered from 1: {}", line);
+                    self.var(VarLabel::new(lit.unsigned_abs() - 1), lit > 0)
+                }
+                "D" => {
+                    let mut r = SddPtr::new_const(false);
+                    for i in 0..num(3) as usize {
+                        let p = node(num(4 + 2 * i));
+                        let s = node(num(5 + 2 * i));
+                        let e = self.and(p, s);
+                        r = self.or(r, e);
+                    }
+                    r
+                }
+                _ => panic!("invalid sdd line: {}", line),
+            };
+            nodes.insert(num(1) as usize, ptr);
+            root = Some(ptr);
+        }
+        match root {
+            Some(r) => r,
+            None => panic!("empty sdd file"),
+        }
+    }
+
+    /// Builds a manager for the vtree in `vtree`, a `.vtree` file, and loads
+    /// the SDD in `sdd`, a `.sdd` file, into it
+    pub fn from_sdd_file(sdd: &str, vtree: &str) -> (SddManager, SddPtr) {
+        let mut man = SddManager::new(vtree::from_vtree_file(vtree));
+        let ptr = man.load_sdd_file(sdd);
+        (man, ptr)
+    }
+
+    /// The text for `ptr` inside the element box of its parent in `to_dot`,
+    /// if it is a constant or a literal
+    fn dot_label(&self, ptr: SddPtr) -> Option<String> {
+        if ptr.is_const() {
+            return Some(String::from(if ptr.is_true() { "T" } else { "F" }));
+        }
+        if !ptr.is_bdd() {
+            return None;
+        }
+        let bdd = ptr.as_bdd_ptr();
+        let bdd_man = self.tbl.bdd_man(ptr.vtree());
+        if !bdd_man.is_var(bdd) {
+            return None;
+        }
+        let lbl = self.tbl.bdd_conv(ptr.vtree())[&bdd.label()];
+        let (_, high) = bdd_man.top_cofactors(bdd, bdd.label());
+        Some(format!("{}{}", if high.is_true() { "" } else { "!" }, lbl.value()))
+    }
+
+    /// Appends the DOT statements for `ptr`, which is neither a constant nor
+    /// a literal, and its descendants to `lines`, and returns its id
+    fn dot_node(&self, ptr: SddPtr, ids: &mut HashMap<SddPtr, usize>, lines: &mut Vec<String>) -> usize {
+        if let Some(id) = ids.get(&ptr) {
+            return *id;
+        }
+        let id = ids.len();
+        ids.insert(ptr, id);
+        if ptr.is_bdd() {
+            let m = self.tbl.bdd_conv(ptr.vtree());
+            let s = self.tbl.bdd_man(ptr.vtree()).print_bdd_lbl(ptr.as_bdd_ptr(), m);
+            lines.push(format!("  n{} [shape=box, label=\"{}\"];", id, s));
+            return id;
+        }
+        lines.push(format!("  n{} [shape=circle, label=\"{}\"];", id, ptr.vtree()));
+        for (i, (p, s)) in self.elements(ptr).into_iter().enumerate() {
+            let mut fields = Vec::new();
+            for &(x, port) in [(p, "p"), (s, "s")].iter() {
+                match self.dot_label(x) {
+                    Some(l) => fields.push(format!("<{}>{}", port, l)),
+                    None => {
+                        let child = self.dot_node(x, ids, lines);
+                        lines.push(format!("  n{}e{}:{} -> n{};", id, i, port, child));
+                        fields.push(format!("<{}>", port));
+                    }
+                }
+            }
+            lines.push(format!("  n{}e{} [shape=record, label=\"{}\"];", id, i, fields.join("|")));
+            lines.push(format!("  n{} -> n{}e{} [arrowhead=none];", id, id, i));
+        }
+        id
+    }
+
+    /// Renders `ptr` in the Graphviz DOT language, in the style of the SDD
+    /// package: each decision node is a circle labeled with its vtree node,
+    /// joined to a box for each element whose two fields hold the prime and
+    /// the sub. A constant or literal is written in its field; any other
+    /// prime or sub is drawn as an edge from the field. As in `to_sdd_file`,
+    /// a negated decision node is drawn as a separate node, and a sub-BDD
+    /// which is not a literal is a box holding its `print_bdd_lbl`.
+    pub fn to_dot(&self, ptr: SddPtr) -> String {
+        let mut lines = Vec::new();
+        match self.dot_label(ptr) {
+            Some(l) => lines.push(format!("  n0 [shape=plaintext, label=\"{}\"];", l)),
+            None => {
+                self.dot_node(ptr, &mut HashMap::new(), &mut lines);
+            }
+        };
+        let mut r = String::from("digraph SDD {\n");
+        for l in lines.iter() {
+            r.push_str(l);
+            r.push('\n');
+        }
+        r.push_str("}\n");
+        r
+    }
+
     /// Evaluate an SDD on a set of input Boolean variable values
     /// TODO: This is *highly* inefficient, fix it
     pub fn eval_sdd(&self, ptr: SddPtr, assgn: &HashMap<VarLabel, bool>) -> bool {
@@ -455,10 +1835,165 @@ impl SddManager {
     }
 
 
+    /// A lazy iterator over a set of disjoint cubes whose disjunction is `ptr`
+    pub fn cubes<'a>(&'a self, ptr: SddPtr) -> SddCubeIter<'a> {
+        SddCubeIter {
+            man: self,
+            stack: vec![(Vec::new(), vec![ptr])],
+        }
+    }
+
+    /// A lazy iterator over every total assignment to the variables of the
+    /// vtree which satisfies `ptr`
+    pub fn models<'a>(&'a self, ptr: SddPtr) -> ModelIter<SddCubeIter<'a>> {
+        let mut vars: Vec<VarLabel> = self.tbl.sdd_to_bdd.keys().cloned().collect();
+        vars.sort_by_key(|v| v.value());
+        ModelIter::new(self.cubes(ptr), vars)
+    }
+
     pub fn sdd_eq(&self, a: SddPtr, b: SddPtr) -> bool {
         a == b
     }
 
+    /// The variables which `ptr` depends on, sorted by label. The support of
+    /// each vtree-leaf BDD is translated back into SDD labels.
+    pub fn support(&self, ptr: SddPtr) -> Vec<VarLabel> {
+        let mut vars = Vec::new();
+        let mut seen = HashSet::new();
+        let mut stack = vec![ptr];
+        while let Some(n) = stack.pop() {
+            if n.is_const() || !seen.insert(n) {
+                continue;
+            }
+            if n.is_bdd() {
+                let conv = self.tbl.bdd_conv(n.vtree());
+                for v in self.tbl.bdd_man(n.vtree()).support(n.as_bdd_ptr()).iter() {
+                    vars.push(conv[v]);
+                }
+            } else {
+                for &(p, s) in self.tbl.sdd_slice_or_panic(n).iter() {
+                    stack.push(p);
+                    stack.push(s);
+                }
+            }
+        }
+        vars.sort_by_key(|v| v.value());
+        vars.dedup();
+        vars
+    }
+
+    /// the literals implied by the satisfiable `ptr`
+    fn essential_helper(&self, ptr: SddPtr,
+                        memo: &mut HashMap<SddPtr, HashSet<Literal>>) -> HashSet<Literal> {
+        if ptr.is_const() {
+            return HashSet::new();
+        }
+        if let Some(r) = memo.get(&ptr) {
+            return r.clone();
+        }
+        let res = if ptr.is_bdd() {
+            let conv = self.tbl.bdd_conv(ptr.vtree());
+            self.tbl
+                .bdd_man(ptr.vtree())
+                .essential_vars(ptr.as_bdd_ptr())
+                .iter()
+                .map(|l| Literal::new(conv[&l.get_label()], l.get_polarity()))
+                .collect()
+        } else {
+            // a literal is implied by a disjunction if it is implied by every
+            // satisfiable element
+            let mut res: Option<HashSet<Literal>> = None;
+            for &(p, s) in self.tbl.sdd_slice_or_panic(ptr).iter() {
+                let s = if ptr.is_compl() { s.neg() } else { s };
+                if s.is_false() {
+                    continue;
+                }
+                let mut elem = self.essential_helper(p, memo);
+                elem.extend(self.essential_helper(s, memo));
+                res = Some(match res {
+                    None => elem,
+                    Some(r) => r.intersection(&elem).cloned().collect(),
+                });
+            }
+            res.unwrap()
+        };
+        memo.insert(ptr, res.clone());
+        res
+    }
+
+    /// The literals implied by `ptr`, sorted by label. An unsatisfiable `ptr`
+    /// has none.
+    pub fn essential_vars(&self, ptr: SddPtr) -> Vec<Literal> {
+        if ptr.is_false() {
+            return Vec::new();
+        }
+        let mut lits: Vec<Literal> = self.essential_helper(ptr, &mut HashMap::new()).into_iter().collect();
+        lits.sort_by_key(|l| l.get_label().value());
+        lits
+    }
+
+    /// The elements of `ptr` viewed as a decomposition at the internal vtree
+    /// node `vtree`, which must contain the vtree node of `ptr`
+    fn elements_at(&self, ptr: SddPtr, vtree: usize) -> Vec<(SddPtr, SddPtr)> {
+        let t = SddPtr::new_const(true);
+        if ptr.vtree() == vtree {
+            self.elements(ptr)
+        } else if ptr.vtree() < vtree {
+            // `ptr` lives in the left subtree, so it is a prime
+            vec![(ptr, t), (ptr.neg(), t.neg())]
+        } else {
+            vec![(t, ptr)]
+        }
+    }
+
+    /// true if `a <= b`; `memo` holds the outcome of every pair already
+    /// decided, so each pair is examined once
+    fn leq_rec(&self, a: SddPtr, b: SddPtr, memo: &mut HashMap<(SddPtr, SddPtr), bool>) -> bool {
+        if a.is_false() || b.is_true() || a == b {
+            return true;
+        } else if a.is_true() || b.is_false() || a == b.neg() {
+            return false;
+        }
+        if let Some(r) = memo.get(&(a, b)) {
+            return *r;
+        }
+        let lca = least_common_ancestor(&self.parent_ptr, a.vtree(), b.vtree());
+        let r = if a.is_bdd() && b.is_bdd() && a.vtree() == b.vtree() {
+            self.tbl.bdd_man(lca).leq(a.as_bdd_ptr(), b.as_bdd_ptr())
+        } else {
+            // the primes and subs share no variables, so `a /\ !b` is empty
+            // exactly when every pair of elements with consistent primes has
+            // entailing subs
+            let a_elems = self.elements_at(a, lca);
+            let b_elems = self.elements_at(b, lca);
+            a_elems.iter().all(|&(p, s)| {
+                b_elems.iter().all(|&(q, t)| {
+                    self.leq_rec(s, t, memo) || self.leq_rec(p, q.neg(), memo)
+                })
+            })
+        };
+        memo.insert((a, b), r);
+        r
+    }
+
+    /// true if `a` implies `b`: every model of `a` is a model of `b`. Unlike
+    /// building `!a \/ b`, this inserts no nodes and stops at the first
+    /// counterexample.
+    pub fn implies(&self, a: SddPtr, b: SddPtr) -> bool {
+        self.leq_rec(a, b, &mut HashMap::new())
+    }
+
+    /// true if `a <= b` in the ordering of functions by their models, which
+    /// is `implies`
+    pub fn leq(&self, a: SddPtr, b: SddPtr) -> bool {
+        self.implies(a, b)
+    }
+
+    /// true if `a` and `b` share no models
+    pub fn disjoint(&self, a: SddPtr, b: SddPtr) -> bool {
+        self.implies(a, b.neg())
+    }
+
     pub fn is_true(&self, a: SddPtr) -> bool {
         a.is_true()
     }
@@ -540,3 +2075,463 @@ fn test_lca() {
     assert_eq!(least_common_ancestor(&par_vec, 2, 1), 1);
     assert_eq!(least_common_ancestor(&par_vec, 4, 6), 5);
 }
+
+#[test]
+fn test_exists_set() {
+    let vars: Vec<VarLabel> = (0..4).map(|x| VarLabel::new(x)).collect();
+    let mut man = SddManager::new(even_split(&vars, 1));
+    // (0 /\ 2) \/ (!1 /\ 3)
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), false);
+    let v2 = man.var(VarLabel::new(2), true);
+    let v3 = man.var(VarLabel::new(3), true);
+    let a = man.and(v0, v2);
+    let b = man.and(v1, v3);
+    let f = man.or(a, b);
+    let res = man.exists_set(f, &[VarLabel::new(0), VarLabel::new(3)]);
+    let expected = man.or(v2, v1);
+    assert!(man.sdd_eq(res, expected), "Got:\n{}\nExpected:\n{}",
+            man.print_sdd(res), man.print_sdd(expected));
+    let res = man.forall(f, &[VarLabel::new(0)]);
+    assert!(man.sdd_eq(res, b), "Got:\n{}\nExpected:\n{}",
+            man.print_sdd(res), man.print_sdd(b));
+    let res = man.exists_set(f, &vars);
+    assert!(man.is_true(res));
+}
+
+#[test]
+fn test_condition() {
+    let vars: Vec<VarLabel> = (0..4).map(|x| VarLabel::new(x)).collect();
+    let mut man = SddManager::new(even_split(&vars, 1));
+    // (0 /\ 2) \/ (!1 /\ 3)
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), false);
+    let v2 = man.var(VarLabel::new(2), true);
+    let v3 = man.var(VarLabel::new(3), true);
+    let a = man.and(v0, v2);
+    let b = man.and(v1, v3);
+    let f = man.or(a, b);
+    let res = man.condition(f, VarLabel::new(0), false);
+    assert!(man.sdd_eq(res, b), "Got:\n{}\nExpected:\n{}",
+            man.print_sdd(res), man.print_sdd(b));
+    let res = man.condition(f, VarLabel::new(3), true);
+    let expected = man.or(a, v1);
+    assert!(man.sdd_eq(res, expected), "Got:\n{}\nExpected:\n{}",
+            man.print_sdd(res), man.print_sdd(expected));
+    let res = man.condition(f.neg(), VarLabel::new(2), false);
+    assert!(man.sdd_eq(res, b.neg()));
+}
+
+#[test]
+fn test_exists() {
+    let vars: Vec<VarLabel> = (0..4).map(|x| VarLabel::new(x)).collect();
+    let mut man = SddManager::new(even_split(&vars, 1));
+    // (0 /\ 2) \/ (!1 /\ 3)
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), false);
+    let v2 = man.var(VarLabel::new(2), true);
+    let v3 = man.var(VarLabel::new(3), true);
+    let a = man.and(v0, v2);
+    let b = man.and(v1, v3);
+    let f = man.or(a, b);
+    // quantifying a prime variable
+    let res = man.exists(f, VarLabel::new(0));
+    let expected = man.or(v2, b);
+    assert!(man.sdd_eq(res, expected), "Got:\n{}\nExpected:\n{}",
+            man.print_sdd(res), man.print_sdd(expected));
+    // quantifying a sub variable
+    let res = man.exists(f, VarLabel::new(3));
+    let expected = man.or(a, v1);
+    assert!(man.sdd_eq(res, expected), "Got:\n{}\nExpected:\n{}",
+            man.print_sdd(res), man.print_sdd(expected));
+    let res = man.exists(v0, VarLabel::new(0));
+    assert!(man.is_true(res));
+}
+
+#[test]
+fn test_apply() {
+    let vars: Vec<VarLabel> = (0..4).map(|x| VarLabel::new(x)).collect();
+    let mut man = SddManager::new(even_split(&vars, 1));
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), false);
+    let v2 = man.var(VarLabel::new(2), true);
+    let v3 = man.var(VarLabel::new(3), true);
+    let a = man.and(v0, v2);
+    let b = man.or(v1, v3);
+    let fns = [a, b, b.neg(), v2, SddPtr::new_const(true)];
+    for &op in BoolOp::all() {
+        for &f in fns.iter() {
+            for &g in fns.iter() {
+                let r = man.apply(op, f, g);
+                for i in 0..16 {
+                    let assgn = hashmap!{VarLabel::new(0) => i & 1 == 1,
+                                         VarLabel::new(1) => i & 2 == 2,
+                                         VarLabel::new(2) => i & 4 == 4,
+                                         VarLabel::new(3) => i & 8 == 8};
+                    let expected = op.eval(man.eval_sdd(f, &assgn), man.eval_sdd(g, &assgn));
+                    assert_eq!(man.eval_sdd(r, &assgn), expected,
+                               "{:?} of\n{}\n{}\ngave {}", op,
+                               man.print_sdd(f), man.print_sdd(g), man.print_sdd(r));
+                }
+            }
+        }
+    }
+    // xor is canonical
+    let x1 = man.xor(a, b);
+    let x2 = man.xor(b.neg(), a);
+    assert!(man.sdd_eq(x1, x2.neg()));
+}
+
+#[test]
+fn test_support() {
+    let vars: Vec<VarLabel> = (0..4).map(|x| VarLabel::new(x)).collect();
+    let mut man = SddManager::new(even_split(&vars, 1));
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let v3 = man.var(VarLabel::new(3), true);
+    // (0 /\ 1) \/ (3 /\ 1)
+    let l = man.and(v0, v1);
+    let r = man.and(v3, v1);
+    let f = man.or(l, r);
+    assert_eq!(man.support(f), vec![VarLabel::new(0), VarLabel::new(1), VarLabel::new(3)]);
+    assert_eq!(man.essential_vars(f), vec![Literal::new(VarLabel::new(1), true)]);
+    let g = man.and(v0.neg(), v3);
+    assert_eq!(man.essential_vars(g),
+               vec![Literal::new(VarLabel::new(0), false), Literal::new(VarLabel::new(3), true)]);
+    assert_eq!(man.essential_vars(g.neg()), vec![]);
+    assert!(man.support(SddPtr::new_const(false)).is_empty());
+}
+
+#[test]
+fn test_implies() {
+    let vars: Vec<VarLabel> = (0..4).map(|x| VarLabel::new(x)).collect();
+    let mut man = SddManager::new(even_split(&vars, 1));
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let v3 = man.var(VarLabel::new(3), true);
+    let a = man.and(v0, v3);
+    let o = man.or(v1, v3);
+    let x = man.xor(v0, v3);
+    let num_nodes = man.num_nodes();
+    assert!(man.implies(a, o));
+    assert!(man.implies(a, v0));
+    assert!(man.implies(a, a));
+    // the models overlap, but neither function implies the other
+    assert!(!man.implies(o, a));
+    assert!(!man.implies(v0, a));
+    assert!(!man.implies(x, o));
+    assert!(!man.implies(o, x));
+    assert!(!man.implies(a, a.neg()));
+    assert!(!man.implies(SddPtr::new_const(true), o));
+    assert_eq!(man.leq(o, a), man.implies(o, a));
+    assert!(man.disjoint(a, v3.neg()));
+    assert!(!man.disjoint(a, o));
+    assert!(man.disjoint(o.neg(), v1));
+    assert!(man.leq(SddPtr::new_const(false), a));
+    assert!(!man.leq(SddPtr::new_const(true), o));
+    assert_eq!(man.num_nodes(), num_nodes);
+}
+
+#[test]
+fn test_models() {
+    let vars: Vec<VarLabel> = (0..4).map(|x| VarLabel::new(x)).collect();
+    let mut man = SddManager::new(even_split(&vars, 1));
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), true);
+    let v2 = man.var(VarLabel::new(2), true);
+    let v3 = man.var(VarLabel::new(3), true);
+    // (0 /\ 2) \/ !(1 \/ 3)
+    let a = man.and(v0, v2);
+    let b = man.or(v1, v3);
+    let f = man.or(a, b.neg());
+    let models: Vec<HashMap<VarLabel, bool>> = man.models(f).collect();
+    // 4 models of (0 /\ 2) plus 4 of !(1 \/ 3), minus the 1 they share
+    assert_eq!(models.len(), 7);
+    for m in models.iter() {
+        assert!(man.eval_sdd(f, m));
+    }
+    assert_eq!(man.models(f.neg()).count(), 9);
+    for m in man.models(f.neg()) {
+        assert!(!man.eval_sdd(f, &m));
+    }
+    // every completion of a cube is a model; check the one which sets all
+    // the free variables to false
+    for c in man.cubes(f) {
+        let mut assgn: HashMap<VarLabel, bool> =
+            vars.iter().map(|v| (*v, false)).collect();
+        for l in c.iter() {
+            assgn.insert(l.get_label(), l.get_polarity());
+        }
+        assert!(man.eval_sdd(f, &assgn));
+    }
+}
+
+#[test]
+fn test_wmc() {
+    let vars: Vec<VarLabel> = (0..4).map(|x| VarLabel::new(x)).collect();
+    let mut man = SddManager::new(even_split(&vars, 1));
+    let v0 = man.var(VarLabel::new(0), true);
+    let v3 = man.var(VarLabel::new(3), true);
+    let f = man.or(v0, v3);
+    let weights = hashmap!{VarLabel::new(0) => (2,3),
+                           VarLabel::new(1) => (5,7),
+                           VarLabel::new(2) => (11,13),
+                           VarLabel::new(3) => (17,19)};
+    let params = BddWmc::new_with_default(0, 1, weights);
+    // (1 and 2 are smoothed) * (all but !0 /\ !3)
+    assert_eq!(man.wmc(f, &params), 12 * 24 * (5 * 36 - 2 * 17));
+    assert_eq!(man.wmc(f.neg(), &params), 12 * 24 * 2 * 17);
+    assert_eq!(man.wmc(SddPtr::new_const(true), &params), 5 * 12 * 24 * 36);
+    assert_eq!(man.wmc(SddPtr::new_const(false), &params), 0);
+}
+
+#[test]
+fn test_sample() {
+    use rand::{SeedableRng, StdRng};
+    let vars: Vec<VarLabel> = (0..4).map(|x| VarLabel::new(x)).collect();
+    let mut man = SddManager::new(even_split(&vars, 1));
+    let v0 = man.var(VarLabel::new(0), true);
+    let v3 = man.var(VarLabel::new(3), true);
+    let f = man.or(v0, v3);
+    let weights = hashmap!{VarLabel::new(0) => (0.2f64, 0.8),
+                           VarLabel::new(1) => (0.5, 0.5),
+                           VarLabel::new(2) => (0.9, 0.1),
+                           VarLabel::new(3) => (0.4, 0.6)};
+    let params = BddWmc::new_with_default(0.0, 1.0, weights);
+    let mut rng: StdRng = SeedableRng::from_seed(&[0usize][..]);
+    let num_samples = 20000;
+    let mut num_v0 = 0;
+    let mut num_v2 = 0;
+    for m in man.sample(f, &params, &mut rng).take(num_samples) {
+        assert_eq!(m.len(), 4);
+        assert!(man.eval_sdd(f, &m));
+        num_v0 += m[&VarLabel::new(0)] as usize;
+        num_v2 += m[&VarLabel::new(2)] as usize;
+    }
+    // Pr(0 | 0 \/ 3) = 0.8 / (1 - 0.2 * 0.4), and 2 is independent of f
+    let freq = num_v0 as f64 / num_samples as f64;
+    assert!((freq - 0.8 / 0.92).abs() < 0.02);
+    assert!((num_v2 as f64 / num_samples as f64 - 0.1).abs() < 0.02);
+    // uniform sampling hits each of the 12 models about equally often
+    let mut uniform: HashMap<Vec<bool>, usize> = HashMap::new();
+    for m in man.sample_uniform(f, &mut rng).take(num_samples) {
+        let key = (0..4).map(|i| m[&VarLabel::new(i)]).collect();
+        *uniform.entry(key).or_insert(0) += 1;
+    }
+    assert_eq!(uniform.len(), 12);
+    for c in uniform.values() {
+        assert!((*c as f64 / num_samples as f64 - 1.0 / 12.0).abs() < 0.02);
+    }
+    assert!(man.sample(SddPtr::new_const(false), &params, &mut rng).next().is_none());
+}
+
+#[test]
+fn test_model_count() {
+    let vars: Vec<VarLabel> = (0..6).map(|x| VarLabel::new(x)).collect();
+    let mut man = SddManager::new(even_split(&vars, 2));
+    let v0 = man.var(VarLabel::new(0), true);
+    let v4 = man.var(VarLabel::new(4), true);
+    let f = man.or(v0, v4);
+    assert_eq!(man.model_count(f), BigUint::from(48u64));
+    assert_eq!(man.model_count(f.neg()), BigUint::from(16u64));
+    assert_eq!(man.model_count(SddPtr::new_const(true)), BigUint::from(64u64));
+    let lbls = [VarLabel::new(0), VarLabel::new(4), VarLabel::new(5)];
+    assert_eq!(man.model_count_vars(f, &lbls), Some(BigUint::from(6u64)));
+    let dup = [VarLabel::new(4), VarLabel::new(0), VarLabel::new(4)];
+    assert_eq!(man.model_count_vars(f, &dup), Some(BigUint::from(3u64)));
+    assert_eq!(man.model_count_vars(f, &[VarLabel::new(0)]), None);
+    assert_eq!(man.model_count_vars(f, &[VarLabel::new(0), VarLabel::new(4), VarLabel::new(9)]), None);
+}
+
+#[test]
+fn test_garbage_collect() {
+    let vars: Vec<VarLabel> = (0..4).map(|x| VarLabel::new(x)).collect();
+    let mut man = SddManager::new(even_split(&vars, 1));
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), false);
+    let v2 = man.var(VarLabel::new(2), true);
+    let v3 = man.var(VarLabel::new(3), true);
+    let a = man.and(v0, v2);
+    let b = man.and(v1, v3);
+    let keep = man.or(a, b);
+    let r = man.ext_ref(keep);
+    let garbage = man.and(v0, v3);
+    let garbage = man.or(garbage, v1);
+    man.and(garbage, v2);
+    let before = man.num_nodes();
+    let freed = man.garbage_collect();
+    assert!(freed > 0);
+    assert_eq!(man.num_nodes(), before - freed);
+
+    // the protected SDD is unchanged, and rebuilding it is canonical
+    let keep = man.get_ext(r);
+    let assgn = hashmap!{
+        VarLabel::new(0) => false, VarLabel::new(1) => false,
+        VarLabel::new(2) => true, VarLabel::new(3) => true};
+    assert!(man.eval_sdd(keep, &assgn));
+    let v0 = man.var(VarLabel::new(0), true);
+    let v1 = man.var(VarLabel::new(1), false);
+    let v2 = man.var(VarLabel::new(2), true);
+    let v3 = man.var(VarLabel::new(3), true);
+    let a = man.and(v0, v2);
+    let b = man.and(v1, v3);
+    let rebuilt = man.or(a, b);
+    assert!(man.sdd_eq(keep, rebuilt));
+
+    man.decref(r);
+    man.garbage_collect();
+    assert_eq!(man.num_nodes(), 0);
+}
+
+#[test]
+fn test_vtree_ops() {
+    let vars: Vec<VarLabel> = (0..5).map(|x| VarLabel::new(x)).collect();
+    // (0 /\ 3) \/ (!1 /\ 4) \/ (2 /\ !3)
+    let build = |man: &mut SddManager| -> SddPtr {
+        let v: Vec<SddPtr> = (0..5).map(|x| man.var(VarLabel::new(x), true)).collect();
+        let a = man.and(v[0], v[3]);
+        let b = man.and(v[1].neg(), v[4]);
+        let c = man.and(v[2], v[3].neg());
+        let ab = man.or(a, b);
+        man.or(ab, c)
+    };
+    let models = |man: &SddManager, f: SddPtr| -> Vec<bool> {
+        (0..32).map(|m| {
+            let assgn = (0..5).map(|i| (VarLabel::new(i), m & (1 << i) != 0)).collect();
+            man.eval_sdd(f, &assgn)
+        }).collect()
+    };
+    // the size of the canonical SDD for the current vtree, compiled afresh
+    let fresh_size = |man: &SddManager| -> usize {
+        let mut fresh = SddManager::new(man.get_vtree().clone());
+        let f = build(&mut fresh);
+        fresh.ext_ref(f);
+        fresh.live_size()
+    };
+    let mut man = SddManager::new(vtree::balanced(&vars));
+    let f = build(&mut man);
+    let expected = models(&man, f);
+    let r = man.ext_ref(f);
+    let r_neg = man.ext_ref(f.neg());
+    // the rewritten SDD is canonical, so undoing an operation restores the size
+    man.garbage_collect();
+    let size = man.num_nodes();
+    man.swap(5);
+    assert_eq!(man.live_size(), fresh_size(&man));
+    man.swap(5);
+    man.garbage_collect();
+    assert_eq!(man.num_nodes(), size);
+    for idx in 0..9 {
+        if vtree::rotate_right(man.get_vtree(), idx).is_some() {
+            man.rotate_right(idx);
+            assert_eq!(models(&man, man.get_ext(r)), expected, "rotate_right {}", idx);
+            assert_eq!(man.live_size(), fresh_size(&man), "rotate_right {}", idx);
+        }
+        if vtree::swap(man.get_vtree(), idx).is_some() {
+            man.swap(idx);
+            assert_eq!(models(&man, man.get_ext(r)), expected, "swap {}", idx);
+            assert_eq!(man.live_size(), fresh_size(&man), "swap {}", idx);
+        }
+        if vtree::rotate_left(man.get_vtree(), idx).is_some() {
+            man.rotate_left(idx);
+            assert_eq!(models(&man, man.get_ext(r)), expected, "rotate_left {}", idx);
+            assert_eq!(man.live_size(), fresh_size(&man), "rotate_left {}", idx);
+        }
+    }
+    let f = man.get_ext(r);
+    assert!(man.sdd_eq(man.get_ext(r_neg), f.neg()));
+    // a rotation only rewrites the nodes at the rotated vtree node and its
+    // right child, so an SDD over a disjoint part of the vtree keeps its pointer
+    let mut man = SddManager::new(vtree::right_linear(&vars));
+    let f = build(&mut man);
+    let r = man.ext_ref(f);
+    let v3 = man.var(VarLabel::new(3), true);
+    let v4 = man.var(VarLabel::new(4), true);
+    let low = man.and(v3, v4.neg());
+    let r_low = man.ext_ref(low);
+    man.rotate_left(3);
+    man.rotate_left(1);
+    assert_eq!(man.get_ext(r_low), low);
+    assert_eq!(models(&man, man.get_ext(r)), expected);
+    let before = man.live_size();
+    man.minimize();
+    assert!(man.live_size() <= before);
+    assert_eq!(models(&man, man.get_ext(r)), expected);
+    let cnt = expected.iter().filter(|x| **x).count();
+    assert_eq!(man.model_count(man.get_ext(r)), BigUint::from(cnt));
+}
+
+#[test]
+fn test_sdd_file() {
+    // x1 /\ x2, as written by the SDD package for a right-linear vtree
+    let sdd = "c a comment\nsdd 5\nL 1 0 1\nL 2 2 2\nL 3 0 -1\nF 4\nD 0 1 2 1 2 3 4\n";
+    let vtree = "vtree 3\nL 0 1\nL 2 2\nI 1 0 2\n";
+    let (mut man, f) = SddManager::from_sdd_file(sdd, vtree);
+    let v1 = man.var(VarLabel::new(0), true);
+    let v2 = man.var(VarLabel::new(1), true);
+    let expected = man.and(v1, v2);
+    assert!(man.sdd_eq(f, expected), "Got:\n{}\nExpected:\n{}",
+            man.print_sdd(f), man.print_sdd(expected));
+
+    // leaves with several variables are written as decision nodes over
+    // right-linear vtrees
+    let vars: Vec<VarLabel> = (0..6).map(|x| VarLabel::new(x)).collect();
+    let mut man = SddManager::new(even_split(&vars, 2));
+    let v: Vec<SddPtr> = (0..6).map(|x| man.var(VarLabel::new(x), true)).collect();
+    let a = man.or(v[0], v[2].neg());
+    let b = man.and(v[1], v[5]);
+    let c = man.or(a, b);
+    let f = man.and(c, v[3]).neg();
+    let sdd = man.to_sdd_file(f);
+    let vtree = vtree::to_vtree_file(man.get_vtree());
+    let loaded = man.load_sdd_file(&sdd);
+    assert!(man.sdd_eq(f, loaded), "Got:\n{}\nExpected:\n{}\nFile:\n{}",
+            man.print_sdd(loaded), man.print_sdd(f), sdd);
+    let (man2, f2) = SddManager::from_sdd_file(&sdd, &vtree);
+    assert_eq!(man2.model_count(f2), man.model_count(f));
+    // the same nodes, up to the order of the elements
+    assert_eq!(man2.to_sdd_file(f2).lines().count(), sdd.lines().count());
+}
+
+#[test]
+fn test_to_dot() {
+    let vars: Vec<VarLabel> = (0..4).map(|x| VarLabel::new(x)).collect();
+    let mut man = SddManager::new(vtree::balanced(&vars));
+    let v: Vec<SddPtr> = (0..4).map(|x| man.var(VarLabel::new(x), true)).collect();
+    let a = man.and(v[0], v[2]);
+    let b = man.and(v[1].neg(), v[3]);
+    let f = man.or(a, b);
+    let dot = man.to_dot(f);
+    assert!(dot.starts_with("digraph SDD {\n"));
+    assert!(dot.ends_with("}\n"));
+    let decls: Vec<&str> = dot.lines().filter(|l| l.contains(" [shape=")).collect();
+    let edges: Vec<&str> = dot.lines().filter(|l| l.contains(" -> ")).collect();
+    let declared = |id: &str| decls.iter().any(|l| l.trim_start().starts_with(&format!("{} [", id)));
+    // one circle per decision node, the root first and at the root of the
+    // vtree, and a record for each of their elements
+    let circles = dot.matches("shape=circle").count();
+    let records = dot.matches("shape=record").count();
+    assert_eq!(circles, 6);
+    assert_eq!(records, 14);
+    assert_eq!(decls.len(), circles + records);
+    assert!(decls[0].contains("n0 [shape=circle, label=\"3\"]"));
+    // every record is joined to its decision node, and every other edge
+    // leads from a prime or sub field to a decision node
+    assert_eq!(edges.iter().filter(|l| l.ends_with("[arrowhead=none];")).count(), records);
+    let fields: Vec<&&str> = edges.iter().filter(|l| !l.contains("arrowhead")).collect();
+    assert_eq!(fields.len(), circles - 1);
+    for e in edges.iter() {
+        let ends: Vec<&str> = e.trim().trim_end_matches(';').split(" -> ").collect();
+        let from = ends[0].split(':').next().unwrap();
+        let to = ends[1].split(' ').next().unwrap();
+        assert!(declared(from) && declared(to), "dangling edge {}", e);
+    }
+    // constants and literals are written in the fields of their box
+    assert!(dot.contains("|<s>T\""));
+    assert!(dot.contains("|<s>F\""));
+    assert!(dot.contains("<p>0|") && dot.contains("<p>!0|"));
+    // a literal is a single node without edges
+    let lit = man.to_dot(v[0].neg());
+    assert_eq!(lit.matches("[shape=plaintext, label=\"!0\"]").count(), 1);
+    assert!(!lit.contains(" -> "));
+}
@@ -110,6 +110,14 @@ impl VarOrder {
         VarLabel::new(*self.pos_to_var.last().unwrap() as u64)
     }
 
+    /// Exchange the positions of `a` and `b` in the order
+    pub fn swap(&mut self, a: VarLabel, b: VarLabel) {
+        let pa = self.get(a);
+        let pb = self.get(b);
+        self.var_to_pos.swap(a.value() as usize, b.value() as usize);
+        self.pos_to_var.swap(pa, pb);
+    }
+
     /// Generate a new variable at the end of the order
     pub fn new_last(&mut self) -> VarLabel {
         let pos = self.pos_to_var.len();
@@ -128,3 +136,15 @@ fn var_order_basics() {
     assert_eq!(order.lt(lbl2, lbl1), false);
     assert_eq!(order.above(lbl2).unwrap(), lbl1);
 }
+
+#[test]
+fn var_order_swap() {
+    let mut order = VarOrder::linear_order(10);
+    let lbl1 = VarLabel::new(4);
+    let lbl2 = VarLabel::new(5);
+    order.swap(lbl1, lbl2);
+    assert_eq!(order.lt(lbl2, lbl1), true);
+    assert_eq!(order.var_at_pos(4), lbl2);
+    assert_eq!(order.var_at_pos(5), lbl1);
+    assert_eq!(order.above(lbl1).unwrap(), lbl2);
+}
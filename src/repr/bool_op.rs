@@ -0,0 +1,173 @@
+//! The sixteen two-input Boolean operators.
+
+/// A two-input Boolean operator. Each operator is identified by its truth
+/// table: bit `2 * a + b` of the discriminant is the value of `op(a, b)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd)]
+pub enum BoolOp {
+    False = 0,
+    Nor = 1,
+    /// `!a /\ b`
+    NotImpliedBy = 2,
+    NotFirst = 3,
+    /// `a /\ !b`
+    NotImplies = 4,
+    NotSecond = 5,
+    Xor = 6,
+    Nand = 7,
+    And = 8,
+    Iff = 9,
+    Second = 10,
+    /// `a => b`
+    Implies = 11,
+    First = 12,
+    /// `b => a`
+    ImpliedBy = 13,
+    Or = 14,
+    True = 15,
+}
+
+/// every operator, indexed by its truth table
+static ALL_OPS: [BoolOp; 16] = [
+    BoolOp::False,
+    BoolOp::Nor,
+    BoolOp::NotImpliedBy,
+    BoolOp::NotFirst,
+    BoolOp::NotImplies,
+    BoolOp::NotSecond,
+    BoolOp::Xor,
+    BoolOp::Nand,
+    BoolOp::And,
+    BoolOp::Iff,
+    BoolOp::Second,
+    BoolOp::Implies,
+    BoolOp::First,
+    BoolOp::ImpliedBy,
+    BoolOp::Or,
+    BoolOp::True,
+];
+
+/// The function of a single argument which is left over once the other
+/// argument of an operator is fixed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unary {
+    Const(bool),
+    Id,
+    Neg,
+}
+
+impl Unary {
+    /// the unary function which maps `false` to `v0` and `true` to `v1`
+    fn new(v0: bool, v1: bool) -> Unary {
+        match (v0, v1) {
+            (false, true) => Unary::Id,
+            (true, false) => Unary::Neg,
+            (v, _) => Unary::Const(v),
+        }
+    }
+}
+
+impl BoolOp {
+    pub fn all() -> &'static [BoolOp] {
+        &ALL_OPS
+    }
+
+    pub fn from_table(table: u8) -> BoolOp {
+        ALL_OPS[(table & 0xF) as usize]
+    }
+
+    pub fn table(&self) -> u8 {
+        *self as u8
+    }
+
+    pub fn eval(&self, a: bool, b: bool) -> bool {
+        let bit = ((a as u8) << 1) | (b as u8);
+        (self.table() >> bit) & 1 == 1
+    }
+
+    /// the operator `!op(a, b)`
+    pub fn neg(&self) -> BoolOp {
+        BoolOp::from_table(!self.table())
+    }
+
+    /// the operator `op(!a, b)`
+    pub fn neg_first(&self) -> BoolOp {
+        let t = self.table();
+        BoolOp::from_table(((t & 0b0011) << 2) | ((t & 0b1100) >> 2))
+    }
+
+    /// the operator `op(a, !b)`
+    pub fn neg_second(&self) -> BoolOp {
+        let t = self.table();
+        BoolOp::from_table(((t & 0b0101) << 1) | ((t & 0b1010) >> 1))
+    }
+
+    /// the operator `op(b, a)`
+    pub fn swap(&self) -> BoolOp {
+        let t = self.table();
+        BoolOp::from_table((t & 0b1001) | ((t & 0b0010) << 1) | ((t & 0b0100) >> 1))
+    }
+
+    pub fn depends_on_first(&self) -> bool {
+        self.neg_first() != *self
+    }
+
+    pub fn depends_on_second(&self) -> bool {
+        self.neg_second() != *self
+    }
+
+    /// the function `b -> op(a, b)`
+    pub fn fix_first(&self, a: bool) -> Unary {
+        Unary::new(self.eval(a, false), self.eval(a, true))
+    }
+
+    /// the function `a -> op(a, b)`
+    pub fn fix_second(&self, b: bool) -> Unary {
+        Unary::new(self.eval(false, b), self.eval(true, b))
+    }
+
+    /// the function `a -> op(a, a)`
+    pub fn diag(&self) -> Unary {
+        Unary::new(self.eval(false, false), self.eval(true, true))
+    }
+
+    /// the function `a -> op(a, !a)`
+    pub fn anti_diag(&self) -> Unary {
+        Unary::new(self.eval(false, true), self.eval(true, false))
+    }
+}
+
+#[test]
+fn test_bool_op_table() {
+    assert!(BoolOp::And.eval(true, true));
+    assert!(!BoolOp::And.eval(true, false));
+    assert!(BoolOp::Implies.eval(false, true));
+    assert!(!BoolOp::Implies.eval(true, false));
+    assert!(BoolOp::ImpliedBy.eval(true, false));
+    assert!(BoolOp::NotImplies.eval(true, false));
+    assert!(BoolOp::NotImpliedBy.eval(false, true));
+    for &op in BoolOp::all() {
+        assert_eq!(BoolOp::from_table(op.table()), op);
+    }
+}
+
+#[test]
+fn test_bool_op_transforms() {
+    let bools = [true, false];
+    for &op in BoolOp::all() {
+        for &a in bools.iter() {
+            for &b in bools.iter() {
+                assert_eq!(op.neg().eval(a, b), !op.eval(a, b));
+                assert_eq!(op.neg_first().eval(a, b), op.eval(!a, b));
+                assert_eq!(op.neg_second().eval(a, b), op.eval(a, !b));
+                assert_eq!(op.swap().eval(a, b), op.eval(b, a));
+            }
+        }
+    }
+    assert_eq!(BoolOp::And.fix_first(true), Unary::Id);
+    assert_eq!(BoolOp::And.fix_first(false), Unary::Const(false));
+    assert_eq!(BoolOp::Xor.fix_second(true), Unary::Neg);
+    assert_eq!(BoolOp::Xor.diag(), Unary::Const(false));
+    assert_eq!(BoolOp::Or.anti_diag(), Unary::Const(true));
+    assert!(!BoolOp::First.depends_on_second());
+    assert!(BoolOp::Iff.depends_on_first());
+}
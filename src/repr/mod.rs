@@ -2,4 +2,8 @@ pub mod bdd;
 pub mod sdd;
 pub mod cnf;
 pub mod boolexpr;
+pub mod bool_op;
+pub mod polynomial;
+pub mod semiring;
 pub mod var_label;
+pub mod vtree;
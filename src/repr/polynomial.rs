@@ -0,0 +1,444 @@
+//! Polynomials over named parameters, used as weights for parametric weighted
+//! model counting. A `Poly` is a handle into an arena of hash-consed
+//! arithmetic terms, so it is `Copy` and can be used directly as a `BddWmc`
+//! weight: a single `wmc` traversal then yields the count as a polynomial
+//! whose subterms are shared exactly as the BDD shares nodes.
+//!
+//! Arenas are created by `scope`, which owns the arena for the duration of
+//! its closure and frees it on return. Handles are tied to the thread and to
+//! the arena they were created in; using one outside of it panics.
+//!
+//! Division is only defined by a non-zero constant, so `BddManager::marginals`
+//! panics on a count which depends on a parameter; use `wmc_grad` and keep
+//! the count and its derivatives as separate polynomials.
+
+use num::traits::{Num, One, Zero};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+/// A node in the term table; children always have smaller indices than
+/// their parents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Term {
+    /// the bits of an `f64` constant
+    Const(u64),
+    Param(usize),
+    Add(usize, usize),
+    Mul(usize, usize),
+}
+
+/// the index of the constant zero in the term table
+const ZERO: usize = 0;
+/// the index of the constant one in the term table
+const ONE: usize = 1;
+
+struct TermTable {
+    /// identifies the arena, so that stale handles are detected
+    id: usize,
+    terms: Vec<Term>,
+    ids: HashMap<Term, usize>,
+    names: Vec<String>,
+    params: HashMap<String, usize>,
+}
+
+impl TermTable {
+    fn new(id: usize) -> TermTable {
+        let mut t = TermTable {
+            id,
+            terms: Vec::new(),
+            ids: HashMap::new(),
+            names: Vec::new(),
+            params: HashMap::new(),
+        };
+        t.get_or_insert(Term::Const((0.0f64).to_bits()));
+        t.get_or_insert(Term::Const((1.0f64).to_bits()));
+        t
+    }
+
+    fn get_or_insert(&mut self, term: Term) -> usize {
+        if let Some(id) = self.ids.get(&term) {
+            return *id;
+        }
+        let id = self.terms.len();
+        self.terms.push(term);
+        self.ids.insert(term, id);
+        id
+    }
+
+    fn constant(&mut self, v: f64) -> usize {
+        // -0.0 and 0.0 have different bits
+        let v = if v == 0.0 { 0.0 } else { v };
+        self.get_or_insert(Term::Const(v.to_bits()))
+    }
+
+    fn as_const(&self, id: usize) -> Option<f64> {
+        match self.terms[id] {
+            Term::Const(bits) => Some(f64::from_bits(bits)),
+            _ => None,
+        }
+    }
+
+    fn add(&mut self, a: usize, b: usize) -> usize {
+        if a == ZERO {
+            return b;
+        } else if b == ZERO {
+            return a;
+        }
+        if let (Some(x), Some(y)) = (self.as_const(a), self.as_const(b)) {
+            return self.constant(x + y);
+        }
+        // the operands are ordered so that `a + b` and `b + a` are shared
+        let (a, b) = if a < b { (a, b) } else { (b, a) };
+        self.get_or_insert(Term::Add(a, b))
+    }
+
+    fn mul(&mut self, a: usize, b: usize) -> usize {
+        if a == ZERO || b == ZERO {
+            return ZERO;
+        } else if a == ONE {
+            return b;
+        } else if b == ONE {
+            return a;
+        }
+        if let (Some(x), Some(y)) = (self.as_const(a), self.as_const(b)) {
+            return self.constant(x * y);
+        }
+        let (a, b) = if a < b { (a, b) } else { (b, a) };
+        self.get_or_insert(Term::Mul(a, b))
+    }
+
+    /// every term reachable from `root`, in increasing order of index, so
+    /// that each term comes after its children
+    fn reachable(&self, root: usize) -> Vec<usize> {
+        let mut seen = vec![false; root + 1];
+        let mut stack = vec![root];
+        let mut r = Vec::new();
+        while let Some(id) = stack.pop() {
+            if seen[id] {
+                continue;
+            }
+            seen[id] = true;
+            r.push(id);
+            match self.terms[id] {
+                Term::Add(a, b) | Term::Mul(a, b) => {
+                    stack.push(a);
+                    stack.push(b);
+                }
+                _ => (),
+            }
+        }
+        r.sort();
+        r
+    }
+}
+
+thread_local! {
+    /// the arenas of the enclosing calls to `scope`, innermost last
+    static ARENAS: RefCell<Vec<TermTable>> = const { RefCell::new(Vec::new()) };
+    static NEXT_ARENA: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Pops the innermost arena when its `scope` returns or unwinds
+struct ArenaGuard;
+
+impl Drop for ArenaGuard {
+    fn drop(&mut self) {
+        ARENAS.with(|a| a.borrow_mut().pop());
+    }
+}
+
+/// Runs `f` with a fresh polynomial arena, which is freed when `f` returns.
+/// Only polynomials created within `f` may be used within it, and none of
+/// them may be used afterwards; in particular the handles of an enclosing
+/// scope cannot be mixed with those of a nested one.
+pub fn scope<R, F: FnOnce() -> R>(f: F) -> R {
+    let id = NEXT_ARENA.with(|n| {
+        let id = n.get();
+        n.set(id + 1);
+        id
+    });
+    ARENAS.with(|a| a.borrow_mut().push(TermTable::new(id)));
+    let _guard = ArenaGuard;
+    f()
+}
+
+/// Runs `f` on the innermost arena, which must be the one `ptrs` belong to
+fn with_table<R, F: FnOnce(&mut TermTable) -> R>(ptrs: &[Poly], f: F) -> R {
+    ARENAS.with(|a| {
+        let mut a = a.borrow_mut();
+        let t = match a.last_mut() {
+            Some(t) => t,
+            None => panic!("polynomials can only be used inside `polynomial::scope`"),
+        };
+        for p in ptrs.iter() {
+            if p.arena != t.id {
+                panic!("polynomial used outside of the scope it was created in");
+            }
+        }
+        f(t)
+    })
+}
+
+/// A monomial, as the sorted multiset of the indices of its parameters
+type Monomial = Vec<usize>;
+
+/// A polynomial over named real-valued parameters. Equality is structural:
+/// two handles are equal exactly when they name the same hash-consed term, so
+/// polynomials built in different ways may have the same expansion yet
+/// compare unequal; compare their `monomials` for equality of expansions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Poly {
+    id: usize,
+    arena: usize,
+    /// handles are only meaningful on the thread which owns the arena
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Poly {
+    fn new(id: usize, arena: usize) -> Poly {
+        Poly { id, arena, _not_send: PhantomData }
+    }
+
+    pub fn constant(v: f64) -> Poly {
+        with_table(&[], |t| Poly::new(t.constant(v), t.id))
+    }
+
+    /// The polynomial consisting of the single parameter `name`
+    pub fn param(name: &str) -> Poly {
+        with_table(&[], |t| {
+            let idx = match t.params.get(name) {
+                Some(idx) => *idx,
+                None => {
+                    let idx = t.names.len();
+                    t.names.push(String::from(name));
+                    t.params.insert(String::from(name), idx);
+                    idx
+                }
+            };
+            Poly::new(t.get_or_insert(Term::Param(idx)), t.id)
+        })
+    }
+
+    /// The value of the polynomial if it is a constant
+    pub fn as_const(&self) -> Option<f64> {
+        with_table(&[*self], |t| t.as_const(self.id))
+    }
+
+    /// Evaluates the polynomial where each parameter takes its value in
+    /// `values`. Each shared subterm is evaluated once, so the cost is linear
+    /// in the size of the term rather than in the number of monomials.
+    pub fn eval(&self, values: &HashMap<String, f64>) -> f64 {
+        with_table(&[*self], |t| {
+            let mut memo: HashMap<usize, f64> = HashMap::new();
+            for id in t.reachable(self.id) {
+                let v = match t.terms[id] {
+                    Term::Const(bits) => f64::from_bits(bits),
+                    Term::Param(p) => match values.get(&t.names[p]) {
+                        Some(v) => *v,
+                        None => panic!("no value for parameter {}", t.names[p]),
+                    },
+                    Term::Add(a, b) => memo[&a] + memo[&b],
+                    Term::Mul(a, b) => memo[&a] * memo[&b],
+                };
+                memo.insert(id, v);
+            }
+            memo[&self.id]
+        })
+    }
+
+    /// Expands the polynomial into a sum of monomials. Each monomial is given
+    /// as a list of `(parameter, exponent)` pairs sorted by parameter name,
+    /// along with its coefficient; the monomials are sorted and have non-zero
+    /// coefficients. The expansion may be exponentially larger than the term.
+    pub fn monomials(&self) -> Vec<(Vec<(String, usize)>, f64)> {
+        with_table(&[*self], |t| {
+            let mut memo: HashMap<usize, BTreeMap<Monomial, f64>> = HashMap::new();
+            for id in t.reachable(self.id) {
+                let mut r: BTreeMap<Monomial, f64> = BTreeMap::new();
+                match t.terms[id] {
+                    Term::Const(bits) => {
+                        r.insert(Vec::new(), f64::from_bits(bits));
+                    }
+                    Term::Param(p) => {
+                        r.insert(vec![p], 1.0);
+                    }
+                    Term::Add(a, b) => {
+                        for (m, c) in memo[&a].iter().chain(memo[&b].iter()) {
+                            *r.entry(m.clone()).or_insert(0.0) += *c;
+                        }
+                    }
+                    Term::Mul(a, b) => {
+                        for (m_a, c_a) in memo[&a].iter() {
+                            for (m_b, c_b) in memo[&b].iter() {
+                                let mut m: Monomial = m_a.iter().chain(m_b.iter()).cloned().collect();
+                                m.sort();
+                                *r.entry(m).or_insert(0.0) += c_a * c_b;
+                            }
+                        }
+                    }
+                };
+                memo.insert(id, r);
+            }
+            let mut res: Vec<(Vec<(String, usize)>, f64)> = memo[&self.id]
+                .iter()
+                .filter(|&(_, c)| *c != 0.0)
+                .map(|(m, c)| {
+                    let mut powers: Vec<(String, usize)> = Vec::new();
+                    for p in m.iter() {
+                        let name = &t.names[*p];
+                        match powers.iter().position(|(n, _)| n == name) {
+                            Some(i) => powers[i].1 += 1,
+                            None => powers.push((name.clone(), 1)),
+                        }
+                    }
+                    powers.sort();
+                    (powers, *c)
+                })
+                .collect();
+            res.sort_by(|a, b| a.0.cmp(&b.0));
+            res
+        })
+    }
+}
+
+impl fmt::Display for Poly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let monomials = self.monomials();
+        if monomials.is_empty() {
+            return write!(f, "0");
+        }
+        for (i, (powers, c)) in monomials.iter().enumerate() {
+            if i > 0 {
+                write!(f, " + ")?;
+            }
+            write!(f, "{}", c)?;
+            for &(ref name, e) in powers.iter() {
+                if e == 1 {
+                    write!(f, "*{}", name)?;
+                } else {
+                    write!(f, "*{}^{}", name, e)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Add for Poly {
+    type Output = Poly;
+    fn add(self, other: Poly) -> Poly {
+        with_table(&[self, other], |t| Poly::new(t.add(self.id, other.id), t.id))
+    }
+}
+
+impl Mul for Poly {
+    type Output = Poly;
+    fn mul(self, other: Poly) -> Poly {
+        with_table(&[self, other], |t| Poly::new(t.mul(self.id, other.id), t.id))
+    }
+}
+
+impl Neg for Poly {
+    type Output = Poly;
+    fn neg(self) -> Poly {
+        self * Poly::constant(-1.0)
+    }
+}
+
+impl Sub for Poly {
+    type Output = Poly;
+    fn sub(self, other: Poly) -> Poly {
+        self + -other
+    }
+}
+
+/// Division is only defined by a non-zero constant
+impl Div for Poly {
+    type Output = Poly;
+    fn div(self, other: Poly) -> Poly {
+        match other.as_const() {
+            Some(c) if c != 0.0 => self * Poly::constant(1.0 / c),
+            _ => panic!("polynomials can only be divided by a non-zero constant"),
+        }
+    }
+}
+
+/// The remainder is only defined between constants
+impl Rem for Poly {
+    type Output = Poly;
+    fn rem(self, other: Poly) -> Poly {
+        match (self.as_const(), other.as_const()) {
+            (Some(a), Some(b)) => Poly::constant(a % b),
+            _ => panic!("the remainder is only defined between constant polynomials"),
+        }
+    }
+}
+
+impl Zero for Poly {
+    fn zero() -> Poly {
+        with_table(&[], |t| Poly::new(ZERO, t.id))
+    }
+
+    /// true only for the constant zero; a polynomial such as `x - x` which
+    /// expands to zero is not detected
+    fn is_zero(&self) -> bool {
+        self.id == ZERO
+    }
+}
+
+impl One for Poly {
+    fn one() -> Poly {
+        with_table(&[], |t| Poly::new(ONE, t.id))
+    }
+}
+
+/// Parses a constant polynomial
+impl Num for Poly {
+    type FromStrRadixErr = <f64 as Num>::FromStrRadixErr;
+    fn from_str_radix(s: &str, radix: u32) -> Result<Poly, Self::FromStrRadixErr> {
+        <f64 as Num>::from_str_radix(s, radix).map(Poly::constant)
+    }
+}
+
+#[test]
+fn test_poly_arith() {
+    scope(|| {
+        let x = Poly::param("x");
+        let y = Poly::param("y");
+        let p = (x + y) * (x - y);
+        let values = hashmap!{String::from("x") => 3.0, String::from("y") => 2.0};
+        assert_eq!(p.eval(&values), 5.0);
+        // x^2 - y^2, with the cross terms cancelled
+        assert_eq!(p.monomials(),
+                   vec![(vec![(String::from("x"), 2)], 1.0),
+                        (vec![(String::from("y"), 2)], -1.0)]);
+        assert_eq!(format!("{}", p), "1*x^2 + -1*y^2");
+        // equality is structural, not by expansion
+        assert!(p != x * x - y * y);
+        assert_eq!(p.monomials(), (x * x - y * y).monomials());
+        // terms are shared regardless of operand order
+        assert_eq!(x + y, y + x);
+        assert_eq!(x * Poly::one() + Poly::zero(), x);
+        assert_eq!(Poly::from_str_radix("2.5", 10).unwrap().as_const(), Some(2.5));
+        assert_eq!(((x + x) / Poly::constant(2.0)).eval(&values), 3.0);
+    });
+}
+
+#[test]
+fn test_poly_scope() {
+    let outer = scope(|| {
+        let x = Poly::param("x");
+        // a nested scope has its own arena
+        let inner = scope(|| Poly::param("y") + Poly::param("x"));
+        assert!(inner.arena != x.arena);
+        x
+    });
+    // the arena is freed with its scope, and handles from it are rejected
+    assert_eq!(ARENAS.with(|a| a.borrow().len()), 0);
+    let escaped = ::std::panic::catch_unwind(|| scope(|| outer + Poly::one()));
+    assert!(escaped.is_err());
+    assert_eq!(ARENAS.with(|a| a.borrow().len()), 0);
+}
@@ -124,6 +124,13 @@ impl SddPtr {
         }
     }
 
+    /// the same node or sub-BDD, labeled with the vtree node `vtree`
+    pub fn with_vtree(&self, vtree: u16) -> SddPtr {
+        let mut v = *self;
+        v.pack.set_vtree(vtree as u32);
+        v
+    }
+
     pub fn idx(&self) -> usize {
         self.idx
     }
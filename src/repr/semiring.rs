@@ -0,0 +1,145 @@
+//! Commutative semirings, which generalize the sum and product used in
+//! weighted model counting.
+
+use num::traits::Num;
+use std::fmt::Debug;
+
+/// A commutative semiring. Weighted model counting in a semiring sums (with
+/// `sum`) over the models the product (with `product`) of the weights of each
+/// literal.
+pub trait Semiring: Clone + Debug {
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn sum(&self, other: &Self) -> Self;
+    fn product(&self, other: &Self) -> Self;
+}
+
+/// The usual sum and product of a numeric type
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RealSemiring<T: Num + Clone + Debug + Copy>(pub T);
+
+impl<T: Num + Clone + Debug + Copy> Semiring for RealSemiring<T> {
+    fn zero() -> Self {
+        RealSemiring(T::zero())
+    }
+
+    fn one() -> Self {
+        RealSemiring(T::one())
+    }
+
+    fn sum(&self, other: &Self) -> Self {
+        RealSemiring(self.0 + other.0)
+    }
+
+    fn product(&self, other: &Self) -> Self {
+        RealSemiring(self.0 * other.0)
+    }
+}
+
+/// Probabilities represented by their natural logarithm, which avoids
+/// underflow on large models. The sum is computed with log-sum-exp.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogSemiring(pub f64);
+
+impl Semiring for LogSemiring {
+    fn zero() -> Self {
+        LogSemiring(f64::NEG_INFINITY)
+    }
+
+    fn one() -> Self {
+        LogSemiring(0.0)
+    }
+
+    fn sum(&self, other: &Self) -> Self {
+        let (hi, lo) = if self.0 > other.0 { (self.0, other.0) } else { (other.0, self.0) };
+        if lo == f64::NEG_INFINITY {
+            LogSemiring(hi)
+        } else {
+            LogSemiring(hi + (lo - hi).exp().ln_1p())
+        }
+    }
+
+    fn product(&self, other: &Self) -> Self {
+        LogSemiring(self.0 + other.0)
+    }
+}
+
+/// The weight of the most probable model (MPE)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaxTimesSemiring(pub f64);
+
+impl Semiring for MaxTimesSemiring {
+    fn zero() -> Self {
+        MaxTimesSemiring(0.0)
+    }
+
+    fn one() -> Self {
+        MaxTimesSemiring(1.0)
+    }
+
+    fn sum(&self, other: &Self) -> Self {
+        MaxTimesSemiring(self.0.max(other.0))
+    }
+
+    fn product(&self, other: &Self) -> Self {
+        MaxTimesSemiring(self.0 * other.0)
+    }
+}
+
+/// The min-plus semiring, i.e. the cost of the cheapest model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TropicalSemiring(pub f64);
+
+impl Semiring for TropicalSemiring {
+    fn zero() -> Self {
+        TropicalSemiring(f64::INFINITY)
+    }
+
+    fn one() -> Self {
+        TropicalSemiring(0.0)
+    }
+
+    fn sum(&self, other: &Self) -> Self {
+        TropicalSemiring(self.0.min(other.0))
+    }
+
+    fn product(&self, other: &Self) -> Self {
+        TropicalSemiring(self.0 + other.0)
+    }
+}
+
+/// Disjunction and conjunction, i.e. satisfiability under the literals whose
+/// weight is true
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoolSemiring(pub bool);
+
+impl Semiring for BoolSemiring {
+    fn zero() -> Self {
+        BoolSemiring(false)
+    }
+
+    fn one() -> Self {
+        BoolSemiring(true)
+    }
+
+    fn sum(&self, other: &Self) -> Self {
+        BoolSemiring(self.0 || other.0)
+    }
+
+    fn product(&self, other: &Self) -> Self {
+        BoolSemiring(self.0 && other.0)
+    }
+}
+
+#[test]
+fn test_log_semiring() {
+    let a = LogSemiring((0.25f64).ln());
+    let b = LogSemiring((0.5f64).ln());
+    assert!((a.sum(&b).0.exp() - 0.75).abs() < 1e-12);
+    assert!((a.product(&b).0.exp() - 0.125).abs() < 1e-12);
+    assert_eq!(a.sum(&LogSemiring::zero()), a);
+    assert_eq!(a.product(&LogSemiring::one()), a);
+    // does not underflow where the real semiring would
+    let tiny = LogSemiring(-2000.0);
+    assert!((tiny.sum(&tiny).0 - (-2000.0 + (2.0f64).ln())).abs() < 1e-9);
+}
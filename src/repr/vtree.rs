@@ -0,0 +1,617 @@
+//! Constructors for common vtree shapes. Each leaf holds a single variable,
+//! except where noted; see also `sdd_manager::even_split`, which produces
+//! leaves holding many variables.
+
+use repr::sdd::VTree;
+use repr::var_label::VarLabel;
+use repr::cnf::Cnf;
+use util::btree::BTree;
+use rand::Rng;
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+
+/// A vtree in which every left child is a leaf, so that the variables are
+/// primes in the order given by `vars`. The SDDs of a right-linear vtree are
+/// OBDDs.
+pub fn right_linear(vars: &[VarLabel]) -> VTree {
+    assert!(!vars.is_empty(), "a vtree requires at least one variable");
+    let mut t = BTree::Leaf(vec![vars[vars.len() - 1]]);
+    for v in vars.iter().rev().skip(1) {
+        t = BTree::Node((), Box::new(BTree::Leaf(vec![*v])), Box::new(t));
+    }
+    t
+}
+
+/// A vtree in which every right child is a leaf
+pub fn left_linear(vars: &[VarLabel]) -> VTree {
+    assert!(!vars.is_empty(), "a vtree requires at least one variable");
+    let mut t = BTree::Leaf(vec![vars[0]]);
+    for v in vars.iter().skip(1) {
+        t = BTree::Node((), Box::new(t), Box::new(BTree::Leaf(vec![*v])));
+    }
+    t
+}
+
+/// A vtree of minimal height, found by splitting `vars` in half until each
+/// part holds a single variable
+pub fn balanced(vars: &[VarLabel]) -> VTree {
+    assert!(!vars.is_empty(), "a vtree requires at least one variable");
+    if vars.len() == 1 {
+        BTree::Leaf(vec![vars[0]])
+    } else {
+        let (l, r) = vars.split_at(vars.len() / 2);
+        BTree::Node((), Box::new(balanced(l)), Box::new(balanced(r)))
+    }
+}
+
+/// A vtree over a random permutation of `vars`, split at random points
+pub fn random<R: Rng>(vars: &[VarLabel], rng: &mut R) -> VTree {
+    assert!(!vars.is_empty(), "a vtree requires at least one variable");
+    fn helper<R: Rng>(vars: &[VarLabel], rng: &mut R) -> VTree {
+        if vars.len() == 1 {
+            BTree::Leaf(vec![vars[0]])
+        } else {
+            let (l, r) = vars.split_at(rng.gen_range(1, vars.len()));
+            let l = helper(l, rng);
+            let r = helper(r, rng);
+            BTree::Node((), Box::new(l), Box::new(r))
+        }
+    }
+    let mut shuffled = vars.to_vec();
+    rng.shuffle(&mut shuffled);
+    helper(&shuffled, rng)
+}
+
+/// the variables of each clause, without repeats
+fn clause_vars(clause: &[::repr::var_label::Literal]) -> Vec<VarLabel> {
+    let mut vars: Vec<VarLabel> = clause.iter().map(|l| l.get_label()).collect();
+    vars.sort_by_key(|v| v.value());
+    vars.dedup();
+    vars
+}
+
+/// Splits `clauses` in two near the middle, at the point which shares the
+/// fewest variables between the halves
+fn best_split(clauses: &[Vec<VarLabel>]) -> usize {
+    let mut left: HashMap<VarLabel, usize> = HashMap::new();
+    let mut right: HashMap<VarLabel, usize> = HashMap::new();
+    for c in clauses.iter() {
+        for v in c.iter() {
+            *right.entry(*v).or_insert(0) += 1;
+        }
+    }
+    let (lo, hi) = (cmp::max(1, clauses.len() / 4), cmp::max(1, 3 * clauses.len() / 4));
+    let mut cut = 0;
+    let mut best = (usize::MAX, lo);
+    for (i, c) in clauses.iter().enumerate().take(hi) {
+        // move clause `i` to the left half
+        for v in c.iter() {
+            let before = left.get(v).cloned().unwrap_or(0) > 0 && right[v] > 0;
+            *left.entry(*v).or_insert(0) += 1;
+            *right.get_mut(v).unwrap() -= 1;
+            let after = right[v] > 0;
+            if after && !before {
+                cut += 1;
+            } else if before && !after {
+                cut -= 1;
+            }
+        }
+        if i + 1 >= lo && cut < best.0 {
+            best = (cut, i + 1);
+        }
+    }
+    best.1
+}
+
+/// Splits off a connected component of the hypergraph whose nodes are
+/// `clauses` and whose hyperedges are the variables, if there is more than
+/// one. The first component goes to the left.
+fn split_component(clauses: &[Vec<VarLabel>]) -> Option<Vec<bool>> {
+    let mut by_var: HashMap<VarLabel, Vec<usize>> = HashMap::new();
+    for (i, c) in clauses.iter().enumerate() {
+        for v in c.iter() {
+            by_var.entry(*v).or_default().push(i);
+        }
+    }
+    let mut left = vec![false; clauses.len()];
+    left[0] = true;
+    let mut stack = vec![0];
+    let mut count = 1;
+    while let Some(i) = stack.pop() {
+        for v in clauses[i].iter() {
+            for &j in by_var[v].iter() {
+                if !left[j] {
+                    left[j] = true;
+                    count += 1;
+                    stack.push(j);
+                }
+            }
+        }
+    }
+    if count < clauses.len() { Some(left) } else { None }
+}
+
+/// the maximum number of refinement passes per bipartition
+const MAX_PASSES: usize = 8;
+
+/// Refines the bipartition `left` of `clauses` to reduce the number of cut
+/// variables with Fiduccia-Mattheyses passes: each pass tentatively moves
+/// every clause once, always taking the unmoved clause whose move removes
+/// the most variables from the cut while keeping both sides within
+/// `[lo, hi]` clauses, and then keeps the best prefix of the moves.
+fn refine_split(clauses: &[Vec<VarLabel>], left: &mut [bool], lo: usize, hi: usize) {
+    for _ in 0..MAX_PASSES {
+        // the number of clauses on (left, right) mentioning each variable
+        let mut count: HashMap<VarLabel, (usize, usize)> = HashMap::new();
+        for (c, &l) in clauses.iter().zip(left.iter()) {
+            for v in c.iter() {
+                let e = count.entry(*v).or_insert((0, 0));
+                if l { e.0 += 1 } else { e.1 += 1 }
+            }
+        }
+        let gain = |c: &Vec<VarLabel>, from_left: bool, count: &HashMap<VarLabel, (usize, usize)>| {
+            let mut g: isize = 0;
+            for v in c.iter() {
+                let (l, r) = count[v];
+                let (from, to) = if from_left { (l, r) } else { (r, l) };
+                if from == 1 && to > 0 {
+                    g += 1;
+                } else if from > 1 && to == 0 {
+                    g -= 1;
+                }
+            }
+            g
+        };
+        let mut num_left = left.iter().filter(|&&l| l).count();
+        let mut moved = vec![false; clauses.len()];
+        let mut moves = Vec::new();
+        let (mut total, mut best) = (0isize, (0isize, 0usize));
+        loop {
+            let mut pick: Option<(isize, usize)> = None;
+            for i in 0..clauses.len() {
+                let new_left = if left[i] { num_left - 1 } else { num_left + 1 };
+                if moved[i] || new_left < lo || new_left > hi {
+                    continue;
+                }
+                let g = gain(&clauses[i], left[i], &count);
+                let better = match pick {
+                    Some((best_g, _)) => g > best_g,
+                    None => true,
+                };
+                if better {
+                    pick = Some((g, i));
+                }
+            }
+            let (g, i) = match pick {
+                Some(p) => p,
+                None => break,
+            };
+            for v in clauses[i].iter() {
+                let e = count.get_mut(v).unwrap();
+                if left[i] { e.0 -= 1; e.1 += 1 } else { e.0 += 1; e.1 -= 1 }
+            }
+            num_left = if left[i] { num_left - 1 } else { num_left + 1 };
+            left[i] = !left[i];
+            moved[i] = true;
+            moves.push(i);
+            total += g;
+            if total > best.0 {
+                best = (total, moves.len());
+            }
+        }
+        // undo the moves after the best prefix
+        for &i in moves[best.1..].iter() {
+            left[i] = !left[i];
+        }
+        if best.0 == 0 {
+            return;
+        }
+    }
+}
+
+/// Bipartitions `clauses`, which are sorted by their position in the
+/// variable order, so that few variables are shared by the two sides. A
+/// disconnected clause hypergraph is split between its components; otherwise
+/// the best split of the sorted clauses is refined by `refine_split`.
+fn min_cut_split(clauses: &[Vec<VarLabel>]) -> (Vec<Vec<VarLabel>>, Vec<Vec<VarLabel>>) {
+    let left = match split_component(clauses) {
+        Some(left) => left,
+        None => {
+            let n = clauses.len();
+            let mid = best_split(clauses);
+            let mut left: Vec<bool> = (0..n).map(|i| i < mid).collect();
+            let (lo, hi) = (cmp::max(1, n / 4), cmp::min(n - 1, cmp::max(1, 3 * n / 4)));
+            refine_split(clauses, &mut left, lo, hi);
+            left
+        }
+    };
+    let mut l = Vec::new();
+    let mut r = Vec::new();
+    for (c, is_left) in clauses.iter().zip(left) {
+        if is_left { l.push(c.clone()) } else { r.push(c.clone()) }
+    }
+    (l, r)
+}
+
+/// Builds the vtree for a dtree node whose clauses are `clauses`, where the
+/// variables in `placed` already appear higher in the vtree. The variables
+/// shared by the two halves of the dtree node (its cutset) are placed above
+/// the vtrees of the halves, so that they are primes.
+fn dtree_vtree(clauses: &[Vec<VarLabel>], placed: &HashSet<VarLabel>) -> Option<VTree> {
+    if clauses.len() == 1 {
+        let local: Vec<VarLabel> = clauses[0].iter().filter(|v| !placed.contains(v)).cloned().collect();
+        return if local.is_empty() { None } else { Some(right_linear(&local)) };
+    }
+    let (l, r) = min_cut_split(clauses);
+    let l_vars: HashSet<VarLabel> = l.iter().flat_map(|c| c.iter().cloned()).collect();
+    let mut cutset: Vec<VarLabel> = r.iter()
+        .flat_map(|c| c.iter().cloned())
+        .filter(|v| l_vars.contains(v) && !placed.contains(v))
+        .collect();
+    cutset.sort_by_key(|v| v.value());
+    cutset.dedup();
+    let mut placed = placed.clone();
+    placed.extend(cutset.iter().cloned());
+    let below = match (dtree_vtree(&l, &placed), dtree_vtree(&r, &placed)) {
+        (Some(l), Some(r)) => Some(BTree::Node((), Box::new(l), Box::new(r))),
+        (Some(t), None) | (None, Some(t)) => Some(t),
+        (None, None) => None,
+    };
+    match (cutset.is_empty(), below) {
+        (true, below) => below,
+        (false, None) => Some(right_linear(&cutset)),
+        (false, Some(b)) => Some(BTree::Node((), Box::new(right_linear(&cutset)), Box::new(b))),
+    }
+}
+
+/// A vtree which follows the clause structure of `cnf`. The clauses are
+/// recursively bipartitioned into a dtree by min-cut partitioning of the
+/// hypergraph whose nodes are the clauses and whose hyperedges are the
+/// variables: each split separates connected components when there are
+/// several, and otherwise refines an initial split of the clauses, sorted by
+/// `Cnf::force_order`, to cut few variables. The variables cut by each split
+/// are then placed above the vtrees of its two sides. Variables which occur
+/// in no clause are placed at the bottom right.
+pub fn from_cnf(cnf: &Cnf) -> VTree {
+    let order = cnf.force_order();
+    let mut clauses: Vec<Vec<VarLabel>> = cnf.clauses()
+        .iter()
+        .map(|c| clause_vars(c))
+        .filter(|c| !c.is_empty())
+        .collect();
+    // sort by the center of gravity of each clause in the order
+    let cog = |c: &Vec<VarLabel>| {
+        c.iter().map(|v| order.get(*v)).sum::<usize>() as f64 / c.len() as f64
+    };
+    clauses.sort_by(|a, b| cog(a).partial_cmp(&cog(b)).unwrap());
+    let tree = if clauses.is_empty() { None } else { dtree_vtree(&clauses, &HashSet::new()) };
+    let used: HashSet<VarLabel> = clauses.iter().flat_map(|c| c.iter().cloned()).collect();
+    let unused: Vec<VarLabel> = (0..cnf.num_vars())
+        .map(|v| VarLabel::new(v as u64))
+        .filter(|v| !used.contains(v))
+        .collect();
+    match (tree, unused.is_empty()) {
+        (Some(t), true) => t,
+        (Some(t), false) => BTree::Node((), Box::new(t), Box::new(right_linear(&unused))),
+        (None, _) => right_linear(&unused),
+    }
+}
+
+/// the number of nodes in `t`
+fn size(t: &VTree) -> usize {
+    match *t {
+        BTree::Leaf(_) => 1,
+        BTree::Node(_, ref l, ref r) => size(l) + size(r) + 1,
+    }
+}
+
+/// Rebuilds `t` with `f` applied to the node at in-order index `idx`, where
+/// the first node of `t` has index `offset`. Returns `None` if `f` does.
+fn modify_at<F>(t: &VTree, idx: usize, offset: usize, f: &F) -> Option<VTree>
+where
+    F: Fn(&VTree) -> Option<VTree>,
+{
+    match *t {
+        BTree::Leaf(_) => if idx == offset { f(t) } else { None },
+        BTree::Node(_, ref l, ref r) => {
+            let pos = offset + size(l);
+            if idx == pos {
+                f(t)
+            } else if idx < pos {
+                modify_at(l, idx, offset, f).map(|l| BTree::Node((), Box::new(l), r.clone()))
+            } else {
+                modify_at(r, idx, pos + 1, f).map(|r| BTree::Node((), l.clone(), Box::new(r)))
+            }
+        }
+    }
+}
+
+/// Rotates the node at in-order index `idx` to the right, turning
+/// `((a, b), c)` into `(a, (b, c))`. Returns `None` if the node or its left
+/// child is a leaf. The in-order index of every node is unchanged, but the
+/// root of the rotated subtree is now the node which was its left child.
+pub fn rotate_right(t: &VTree, idx: usize) -> Option<VTree> {
+    modify_at(t, idx, 0, &|n| match *n {
+        BTree::Node(_, ref w, ref c) => match **w {
+            BTree::Node(_, ref a, ref b) => {
+                let x = BTree::Node((), b.clone(), c.clone());
+                Some(BTree::Node((), a.clone(), Box::new(x)))
+            }
+            BTree::Leaf(_) => None,
+        },
+        BTree::Leaf(_) => None,
+    })
+}
+
+/// Rotates the node at in-order index `idx` to the left, turning
+/// `(a, (b, c))` into `((a, b), c)`; the inverse of `rotate_right`. Returns
+/// `None` if the node or its right child is a leaf.
+pub fn rotate_left(t: &VTree, idx: usize) -> Option<VTree> {
+    modify_at(t, idx, 0, &|n| match *n {
+        BTree::Node(_, ref a, ref x) => match **x {
+            BTree::Node(_, ref b, ref c) => {
+                let w = BTree::Node((), a.clone(), b.clone());
+                Some(BTree::Node((), Box::new(w), c.clone()))
+            }
+            BTree::Leaf(_) => None,
+        },
+        BTree::Leaf(_) => None,
+    })
+}
+
+/// Swaps the children of the node at in-order index `idx`. Returns `None`
+/// if the node is a leaf.
+pub fn swap(t: &VTree, idx: usize) -> Option<VTree> {
+    modify_at(t, idx, 0, &|n| match *n {
+        BTree::Node(_, ref l, ref r) => Some(BTree::Node((), r.clone(), l.clone())),
+        BTree::Leaf(_) => None,
+    })
+}
+
+/// Splits every leaf which holds several variables into a right-linear vtree
+/// over them, in order, so that each leaf holds a single variable
+pub fn expand_leaves(t: &VTree) -> VTree {
+    match *t {
+        BTree::Leaf(ref vars) => right_linear(vars),
+        BTree::Node(_, ref l, ref r) => {
+            BTree::Node((), Box::new(expand_leaves(l)), Box::new(expand_leaves(r)))
+        }
+    }
+}
+
+/// the comments which open every `.vtree` file written by the SDD package
+const VTREE_FILE_HEADER: &str = "c ids of vtree nodes start at 0
+c ids of variables start at 1
+c vtree nodes appear bottom-up, children before parents
+c
+c file syntax:
+c vtree number-of-nodes-in-vtree
+c L id-of-leaf-vtree-node id-of-variable
+c I id-of-internal-vtree-node id-of-left-child id-of-right-child
+c
+";
+
+/// Writes `t` in the `.vtree` format of the SDD package, after splitting its
+/// leaves with `expand_leaves`. Nodes are numbered by their in-order index
+/// and variables are numbered from 1, so that `VarLabel(0)` is variable 1.
+pub fn to_vtree_file(t: &VTree) -> String {
+    fn helper(t: &VTree, offset: usize, lines: &mut Vec<String>) -> usize {
+        match *t {
+            BTree::Leaf(ref vars) => {
+                lines.push(format!("L {} {}", offset, vars[0].value() + 1));
+                offset
+            }
+            BTree::Node(_, ref l, ref r) => {
+                let pos = offset + size(l);
+                let l_id = helper(l, offset, lines);
+                let r_id = helper(r, pos + 1, lines);
+                lines.push(format!("I {} {} {}", pos, l_id, r_id));
+                pos
+            }
+        }
+    }
+    let t = expand_leaves(t);
+    let mut lines = Vec::new();
+    helper(&t, 0, &mut lines);
+    let mut r = String::from(VTREE_FILE_HEADER);
+    r.push_str(&format!("vtree {}\n", lines.len()));
+    for l in lines.iter() {
+        r.push_str(l);
+        r.push('\n');
+    }
+    r
+}
+
+/// Parses a vtree in the `.vtree` format of the SDD package; see
+/// `to_vtree_file`. The last node in the file is the root. Panics if `s` is
+/// malformed.
+pub fn from_vtree_file(s: &str) -> VTree {
+    let mut nodes: HashMap<usize, VTree> = HashMap::new();
+    let mut root = None;
+    for line in s.lines() {
+        let tok: Vec<&str> = line.split_whitespace().collect();
+        if tok.is_empty() || tok[0] == "c" || tok[0] == "vtree" {
+            continue;
+        }
+        let num = |i: usize| -> usize {
+            match tok.get(i).and_then(|t| t.parse().ok()) {
+                Some(n) => n,
+                None => panic!("invalid vtree line: {}", line),
+            }
+        };
+        let (id, n) = match tok[0] {
+            "L" => {
+                assert!(num(2) > 0, "variables are numbered from 1: {}", line);
+                (num(1), BTree::Leaf(vec![VarLabel::new(num(2) as u64 - 1)]))
+            }
+            "I" => {
+                let l = nodes.remove(&num(2)).expect("undefined left child");
+                let r = nodes.remove(&num(3)).expect("undefined right child");
+                (num(1), BTree::Node((), Box::new(l), Box::new(r)))
+            }
+            _ => panic!("invalid vtree line: {}", line),
+        };
+        nodes.insert(id, n);
+        root = Some(id);
+    }
+    match root {
+        Some(id) => nodes.remove(&id).unwrap(),
+        None => panic!("empty vtree file"),
+    }
+}
+
+/// Renders `t` in the Graphviz DOT language. Each internal node is labeled
+/// with its in-order index, as used for the vtree of an `SddPtr`, and each
+/// leaf with its index and variables.
+pub fn to_dot(t: &VTree) -> String {
+    let mut r = String::from("digraph VTree {\n");
+    for (idx, n) in t.in_order_iter().enumerate() {
+        match *n {
+            BTree::Leaf(ref vars) => {
+                let vars: Vec<String> = vars.iter().map(|v| format!("{}", v.value())).collect();
+                r.push_str(&format!("  n{} [shape=box, label=\"{}: {}\"];\n", idx, idx, vars.join(" ")));
+            }
+            BTree::Node(_, _, _) => {
+                r.push_str(&format!("  n{} [shape=circle, label=\"{}\"];\n", idx, idx));
+            }
+        }
+    }
+    fn edges(t: &VTree, offset: usize, r: &mut String) -> usize {
+        match *t {
+            BTree::Leaf(_) => offset,
+            BTree::Node(_, ref left, ref right) => {
+                let pos = offset + size(left);
+                let l_id = edges(left, offset, r);
+                let r_id = edges(right, pos + 1, r);
+                r.push_str(&format!("  n{} -> n{};\n  n{} -> n{};\n", pos, l_id, pos, r_id));
+                pos
+            }
+        }
+    }
+    edges(t, 0, &mut r);
+    r.push_str("}\n");
+    r
+}
+
+#[cfg(test)]
+fn leaf_vars(t: &VTree) -> Vec<VarLabel> {
+    let mut r = Vec::new();
+    for n in t.in_order_iter() {
+        match n {
+            &BTree::Leaf(ref l) => r.extend(l.iter().cloned()),
+            &BTree::Node(_, _, _) => (),
+        }
+    }
+    r
+}
+
+#[test]
+fn test_vtree_shapes() {
+    let vars: Vec<VarLabel> = (0..5).map(|x| VarLabel::new(x)).collect();
+    for t in [right_linear(&vars), left_linear(&vars), balanced(&vars)].iter() {
+        assert_eq!(leaf_vars(t), vars);
+        assert_eq!(t.in_order_iter().count(), 9);
+    }
+    match right_linear(&vars) {
+        BTree::Node(_, ref l, _) => assert_eq!(leaf_vars(l), vec![VarLabel::new(0)]),
+        _ => panic!("expected a node"),
+    }
+    match left_linear(&vars) {
+        BTree::Node(_, _, ref r) => assert_eq!(leaf_vars(r), vec![VarLabel::new(4)]),
+        _ => panic!("expected a node"),
+    }
+    let mut rng = ::rand::thread_rng();
+    let mut shuffled = leaf_vars(&random(&vars, &mut rng));
+    shuffled.sort_by_key(|v| v.value());
+    assert_eq!(shuffled, vars);
+}
+
+#[test]
+fn test_vtree_ops() {
+    let vars: Vec<VarLabel> = (0..3).map(|x| VarLabel::new(x)).collect();
+    // ((0, 1), 2): the root has in-order index 3
+    let t = left_linear(&vars);
+    let r = rotate_right(&t, 3).unwrap();
+    assert_eq!(format!("{:?}", r), format!("{:?}", right_linear(&vars)));
+    // the new root has the index of the old left child
+    let back = rotate_left(&r, 1).unwrap();
+    assert_eq!(format!("{:?}", back), format!("{:?}", t));
+    assert!(rotate_left(&t, 3).is_none());
+    assert!(rotate_right(&t, 0).is_none());
+    let s = swap(&t, 1).unwrap();
+    assert_eq!(leaf_vars(&s), vec![VarLabel::new(1), VarLabel::new(0), VarLabel::new(2)]);
+}
+
+#[test]
+fn test_vtree_from_cnf() {
+    use repr::var_label::Literal;
+    let lit = |v: u64| Literal::new(VarLabel::new(v), true);
+    // a chain of clauses; 5 occurs in no clause
+    let cnf = Cnf::new(vec![vec![lit(0), lit(1)], vec![lit(1), lit(2)],
+                            vec![lit(2), lit(3)], vec![lit(3), lit(4)],
+                            vec![lit(4), lit(6)]]);
+    let t = from_cnf(&cnf);
+    let mut vars = leaf_vars(&t);
+    vars.sort_by_key(|v| v.value());
+    let expected: Vec<VarLabel> = (0..7).map(|v| VarLabel::new(v)).collect();
+    assert_eq!(vars, expected);
+    // the unused variable is placed at the bottom right
+    match t {
+        BTree::Node(_, _, ref r) => assert_eq!(leaf_vars(r), vec![VarLabel::new(5)]),
+        _ => panic!("expected a node"),
+    }
+}
+
+#[test]
+fn test_min_cut_split() {
+    let vars = |vs: &[u64]| -> Vec<VarLabel> { vs.iter().map(|v| VarLabel::new(*v)).collect() };
+    let cut = |l: &Vec<Vec<VarLabel>>, r: &Vec<Vec<VarLabel>>| {
+        let l_vars: HashSet<VarLabel> = l.iter().flat_map(|c| c.iter().cloned()).collect();
+        let r_vars: HashSet<VarLabel> = r.iter().flat_map(|c| c.iter().cloned()).collect();
+        l_vars.intersection(&r_vars).count()
+    };
+    // two triangles over {0, 1, 2} and {3, 4, 5} joined by the clause (2, 3),
+    // interleaved so that no contiguous split separates them
+    let clauses = vec![vars(&[0, 1]), vars(&[3, 4]), vars(&[1, 2]), vars(&[4, 5]),
+                       vars(&[0, 2]), vars(&[3, 5]), vars(&[2, 3])];
+    let (l, r) = min_cut_split(&clauses);
+    assert_eq!(l.len() + r.len(), clauses.len());
+    assert!(l.len() >= 2 && r.len() >= 2);
+    assert_eq!(cut(&l, &r), 1);
+    // without the bridge the components are split apart
+    let (l, r) = min_cut_split(&clauses[..6]);
+    assert_eq!(cut(&l, &r), 0);
+    assert_eq!(l.len(), 3);
+}
+
+#[test]
+fn test_vtree_file() {
+    let vars: Vec<VarLabel> = (0..3).map(|x| VarLabel::new(x)).collect();
+    let t = right_linear(&vars);
+    let f = to_vtree_file(&t);
+    assert!(f.ends_with("vtree 5\nL 0 1\nL 2 2\nL 4 3\nI 3 2 4\nI 1 0 3\n"), "got:\n{}", f);
+    assert_eq!(format!("{:?}", from_vtree_file(&f)), format!("{:?}", t));
+    // leaves with several variables are split into right-linear vtrees
+    let t = BTree::Node((), Box::new(BTree::Leaf(vec![VarLabel::new(0), VarLabel::new(1)])),
+                        Box::new(BTree::Leaf(vec![VarLabel::new(2)])));
+    let expected = BTree::Node((), Box::new(right_linear(&vars[0..2])),
+                               Box::new(BTree::Leaf(vec![VarLabel::new(2)])));
+    assert_eq!(format!("{:?}", from_vtree_file(&to_vtree_file(&t))), format!("{:?}", expected));
+}
+
+#[test]
+fn test_vtree_to_dot() {
+    let t = BTree::Node((), Box::new(BTree::Leaf(vec![VarLabel::new(0), VarLabel::new(1)])),
+                        Box::new(BTree::Leaf(vec![VarLabel::new(2)])));
+    let dot = to_dot(&t);
+    assert!(dot.starts_with("digraph VTree {\n"));
+    assert!(dot.ends_with("}\n"));
+    // a box for each leaf, listing its variables, and a circle for the root
+    assert_eq!(dot.matches("shape=box").count(), 2);
+    assert_eq!(dot.matches("shape=circle").count(), 1);
+    assert!(dot.contains("label=\"0: 0 1\"") && dot.contains("label=\"2: 2\""));
+    // an edge from the root to each of its children
+    let edges: Vec<&str> = dot.lines().filter(|l| l.contains(" -> ")).collect();
+    assert_eq!(edges.len(), 2);
+    assert!(edges.iter().all(|l| l.trim().starts_with("n1 -> ")));
+}
@@ -1,13 +1,18 @@
 extern crate ddrs;
 use ddrs::*;
-use manager::bdd_manager::BddManager;
+use manager::bdd_manager::{BddManager, BddWmc};
 use repr::boolexpr::BoolExpr;
+use repr::bool_op::BoolOp;
+use repr::semiring::*;
 use manager::sdd_manager::{SddManager, even_split};
 use manager::var_order::VarOrder;
-use repr::var_label::VarLabel;
+use repr::var_label::{VarLabel, Literal};
 use std::collections::HashMap;
 use repr::cnf::Cnf;
+use repr::vtree;
 extern crate rand;
+extern crate num;
+use num::BigUint;
 use rand::SeedableRng;
 
 /// A convenient wrapper for generating maps
@@ -341,3 +346,548 @@ pub fn random_sdd() {
         assert!(man.sdd_eq(r, new_r));
     }
 }
+
+/// check that quantifying a set of variables from an SDD agrees with the BDD
+/// for the same function
+#[test]
+pub fn rand_sdd_exists() {
+    let mut rng = rand::StdRng::new().unwrap();
+    rng.reseed(&[0]);
+    for _ in 1..20 {
+        let num_vars = 10;
+        let cnf = BoolExpr::rand_cnf(&mut rng, num_vars, 20);
+        let v : Vec<VarLabel> =
+            (0..num_vars).map(|x| VarLabel::new(x as u64)).collect();
+        let quantified = vec![VarLabel::new(1), VarLabel::new(4), VarLabel::new(8)];
+        let mut sdd_man = SddManager::new(even_split(&v, 2));
+        let mut bdd_man = BddManager::new_default_order(num_vars);
+        let sdd = sdd_man.from_boolexpr(&cnf);
+        let sdd = sdd_man.exists_set(sdd, &quantified);
+        let bdd = bdd_man.from_boolexpr(&cnf);
+        let bdd = bdd_man.exists_set(bdd, &quantified);
+        for _ in 1..30 {
+            let assgn = random_assignment(num_vars);
+            assert_eq!(sdd_man.eval_sdd(sdd, &assgn), bdd_man.eval_bdd(bdd, &assgn),
+                       "Not equal: {:?}\n{}", cnf, sdd_man.print_sdd(sdd)
+            );
+        }
+    }
+}
+
+/// check that the fused relational product agrees with conjoining and then
+/// quantifying
+#[test]
+pub fn rand_bdd_and_exists() {
+    let mut rng = rand::StdRng::new().unwrap();
+    rng.reseed(&[0]);
+    for _ in 1..20 {
+        let num_vars = 12;
+        let cnf1 = BoolExpr::rand_cnf(&mut rng, num_vars, 10);
+        let cnf2 = BoolExpr::rand_cnf(&mut rng, num_vars, 10);
+        let quantified = vec![VarLabel::new(0), VarLabel::new(3), VarLabel::new(7),
+                              VarLabel::new(11)];
+        let mut man = BddManager::new_default_order(num_vars);
+        let f = man.from_boolexpr(&cnf1);
+        let g = man.from_boolexpr(&cnf2);
+        let r = man.and_exists(f, g, &quantified);
+        let conj = man.and(f, g);
+        let expected = man.exists_set(conj, &quantified);
+        assert!(man.eq_bdd(r, expected), "Not eq:\nGot: {}\nExpected: {}",
+                man.print_bdd(r), man.print_bdd(expected));
+    }
+}
+
+/// check that `ite` agrees with its definition in terms of conjunction and
+/// disjunction
+#[test]
+pub fn rand_bdd_ite() {
+    let mut rng = rand::StdRng::new().unwrap();
+    rng.reseed(&[0]);
+    for _ in 1..20 {
+        let num_vars = 12;
+        let mut man = BddManager::new_default_order(num_vars);
+        let f = man.from_boolexpr(&BoolExpr::rand_cnf(&mut rng, num_vars, 8));
+        let g = man.from_boolexpr(&BoolExpr::rand_cnf(&mut rng, num_vars, 8));
+        let h = man.from_boolexpr(&BoolExpr::rand_cnf(&mut rng, num_vars, 8));
+        let r = man.ite(f, g, h);
+        let fg = man.and(f, g);
+        let nf = man.negate(f);
+        let nfh = man.and(nf, h);
+        let expected = man.or(fg, nfh);
+        assert!(man.eq_bdd(r, expected), "Not eq:\nGot: {}\nExpected: {}",
+                man.print_bdd(r), man.print_bdd(expected));
+        let x = man.xor(f, g);
+        let i = man.iff(f, g);
+        assert!(man.eq_bdd(x, i.neg()));
+    }
+}
+
+/// check that every binary operator gives the same function on SDDs as on
+/// BDDs
+#[test]
+pub fn rand_apply() {
+    let mut rng = rand::StdRng::new().unwrap();
+    rng.reseed(&[0]);
+    for _ in 1..10 {
+        let num_vars = 10;
+        let cnf1 = BoolExpr::rand_cnf(&mut rng, num_vars, 8);
+        let cnf2 = BoolExpr::rand_cnf(&mut rng, num_vars, 8);
+        let v : Vec<VarLabel> =
+            (0..num_vars).map(|x| VarLabel::new(x as u64)).collect();
+        let mut sdd_man = SddManager::new(even_split(&v, 2));
+        let mut bdd_man = BddManager::new_default_order(num_vars);
+        let sdd1 = sdd_man.from_boolexpr(&cnf1);
+        let sdd2 = sdd_man.from_boolexpr(&cnf2);
+        let bdd1 = bdd_man.from_boolexpr(&cnf1);
+        let bdd2 = bdd_man.from_boolexpr(&cnf2);
+        for &op in BoolOp::all() {
+            let sdd = sdd_man.apply(op, sdd1, sdd2);
+            let bdd = bdd_man.apply(op, bdd1, bdd2);
+            for _ in 1..30 {
+                let assgn = random_assignment(num_vars);
+                assert_eq!(sdd_man.eval_sdd(sdd, &assgn), bdd_man.eval_bdd(bdd, &assgn),
+                           "{:?} not equal:\n{}\n{}", op,
+                           sdd_man.print_sdd(sdd), bdd_man.print_bdd(bdd));
+            }
+        }
+    }
+}
+
+/// check the implication and disjointness checks against the result of
+/// building the conjunction
+#[test]
+pub fn rand_implies() {
+    let mut rng = rand::StdRng::new().unwrap();
+    rng.reseed(&[0]);
+    for _ in 1..20 {
+        let num_vars = 10;
+        let cnf1 = BoolExpr::rand_cnf(&mut rng, num_vars, 6);
+        let cnf2 = BoolExpr::rand_cnf(&mut rng, num_vars, 6);
+        let v : Vec<VarLabel> =
+            (0..num_vars).map(|x| VarLabel::new(x as u64)).collect();
+        let mut sdd_man = SddManager::new(even_split(&v, 2));
+        let mut bdd_man = BddManager::new_default_order(num_vars);
+        let sdd1 = sdd_man.from_boolexpr(&cnf1);
+        let sdd2 = sdd_man.from_boolexpr(&cnf2);
+        let bdd1 = bdd_man.from_boolexpr(&cnf1);
+        let bdd2 = bdd_man.from_boolexpr(&cnf2);
+        let sdd_and = sdd_man.and(sdd1, sdd2);
+        let sdd_or = sdd_man.or(sdd1, sdd2);
+        let bdd_and = bdd_man.and(bdd1, bdd2);
+        let bdd_or = bdd_man.or(bdd1, bdd2);
+        let pairs = [(sdd1, sdd2, bdd1, bdd2), (sdd2, sdd1, bdd2, bdd1),
+                     (sdd_and, sdd1, bdd_and, bdd1), (sdd1, sdd_or, bdd1, bdd_or),
+                     (sdd_or, sdd_and, bdd_or, bdd_and)];
+        for &(s1, s2, b1, b2) in pairs.iter() {
+            let counter = bdd_man.and(b1, b2.neg());
+            let both = bdd_man.and(b1, b2);
+            assert_eq!(bdd_man.implies(b1, b2), bdd_man.is_false(counter));
+            assert_eq!(sdd_man.implies(s1, s2), bdd_man.is_false(counter),
+                       "implication differs:\n{}\n{}",
+                       sdd_man.print_sdd(s1), sdd_man.print_sdd(s2));
+            assert_eq!(bdd_man.disjoint(b1, b2), bdd_man.is_false(both));
+            assert_eq!(sdd_man.disjoint(s1, s2), bdd_man.is_false(both));
+        }
+    }
+}
+
+/// check that BDDs and SDDs agree on the support and essential literals, and
+/// that each essential literal is implied
+#[test]
+pub fn rand_support() {
+    let mut rng = rand::StdRng::new().unwrap();
+    rng.reseed(&[0]);
+    for _ in 1..20 {
+        let num_vars = 10;
+        let cnf = BoolExpr::rand_cnf(&mut rng, num_vars, 5);
+        let v : Vec<VarLabel> =
+            (0..num_vars).map(|x| VarLabel::new(x as u64)).collect();
+        let mut sdd_man = SddManager::new(even_split(&v, 2));
+        let mut bdd_man = BddManager::new_default_order(num_vars);
+        let sdd = sdd_man.from_boolexpr(&cnf);
+        let bdd = bdd_man.from_boolexpr(&cnf);
+        let support = bdd_man.support(bdd);
+        assert_eq!(sdd_man.support(sdd), support, "support differs for {:?}", cnf);
+        for lbl in v.iter() {
+            let (lo, hi) = (bdd_man.condition(bdd, *lbl, false), bdd_man.condition(bdd, *lbl, true));
+            assert_eq!(support.contains(lbl), lo != hi);
+        }
+        let essential = bdd_man.essential_vars(bdd);
+        assert_eq!(sdd_man.essential_vars(sdd), essential, "essential differs for {:?}", cnf);
+        for lbl in v.iter() {
+            for &polarity in [true, false].iter() {
+                let lit = bdd_man.var(*lbl, polarity);
+                let implied = !bdd_man.is_false(bdd) && bdd_man.implies(bdd, lit);
+                assert_eq!(essential.contains(&Literal::new(*lbl, polarity)), implied);
+            }
+        }
+    }
+}
+
+/// check that conditioning and quantifying a single variable agree between
+/// BDDs and SDDs
+#[test]
+pub fn rand_sdd_condition_exists() {
+    let mut rng = rand::StdRng::new().unwrap();
+    rng.reseed(&[0]);
+    for _ in 1..20 {
+        let num_vars = 10;
+        let cnf = BoolExpr::rand_cnf(&mut rng, num_vars, 12);
+        let v : Vec<VarLabel> =
+            (0..num_vars).map(|x| VarLabel::new(x as u64)).collect();
+        let mut sdd_man = SddManager::new(even_split(&v, 2));
+        let mut bdd_man = BddManager::new_default_order(num_vars);
+        let sdd = sdd_man.from_boolexpr(&cnf);
+        let bdd = bdd_man.from_boolexpr(&cnf);
+        for lbl in v.iter() {
+            let results = [(sdd_man.condition(sdd, *lbl, true), bdd_man.condition(bdd, *lbl, true)),
+                           (sdd_man.condition(sdd, *lbl, false), bdd_man.condition(bdd, *lbl, false)),
+                           (sdd_man.exists(sdd, *lbl), bdd_man.exists(bdd, *lbl))];
+            for &(s, b) in results.iter() {
+                for _ in 1..20 {
+                    let assgn = random_assignment(num_vars);
+                    assert_eq!(sdd_man.eval_sdd(s, &assgn), bdd_man.eval_bdd(b, &assgn),
+                               "{:?} not equal:\n{}\n{}", lbl,
+                               sdd_man.print_sdd(s), bdd_man.print_bdd(b));
+                }
+            }
+        }
+    }
+}
+
+/// check that SDDs compiled with each kind of vtree have the same number of
+/// models as the BDD
+#[test]
+pub fn rand_vtrees() {
+    let mut rng = rand::StdRng::new().unwrap();
+    rng.reseed(&[0]);
+    for _ in 1..10 {
+        let num_vars = 8;
+        let cnf = Cnf::rand_cnf(&mut rng, num_vars, 10);
+        let v : Vec<VarLabel> =
+            (0..num_vars).map(|x| VarLabel::new(x as u64)).collect();
+        let mut bdd_man = BddManager::new_default_order(num_vars);
+        let bdd = bdd_man.from_cnf(&cnf);
+        let cnt = bdd_man.model_count(bdd);
+        let vtrees = [vtree::right_linear(&v), vtree::left_linear(&v), vtree::balanced(&v),
+                      vtree::random(&v, &mut rng), vtree::from_cnf(&cnf)];
+        for t in vtrees.iter() {
+            let mut sdd_man = SddManager::new(t.clone());
+            let sdd = sdd_man.from_cnf(&cnf);
+            assert_eq!(sdd_man.model_count(sdd), cnt, "count differs for {:?} with {:?}", cnf, t);
+        }
+    }
+}
+
+/// check that vtree search preserves the model count of a referenced SDD
+#[test]
+pub fn rand_sdd_minimize() {
+    let mut rng = rand::StdRng::new().unwrap();
+    rng.reseed(&[0]);
+    for _ in 1..5 {
+        let num_vars = 6;
+        let cnf = Cnf::rand_cnf(&mut rng, num_vars, 8);
+        let v : Vec<VarLabel> =
+            (0..num_vars).map(|x| VarLabel::new(x as u64)).collect();
+        let mut bdd_man = BddManager::new_default_order(num_vars);
+        let bdd = bdd_man.from_cnf(&cnf);
+        let cnt = bdd_man.model_count(bdd);
+        let mut sdd_man = SddManager::new(vtree::right_linear(&v));
+        let sdd = sdd_man.from_cnf(&cnf);
+        let r = sdd_man.ext_ref(sdd);
+        assert!(!sdd_man.minimize_if_needed());
+        sdd_man.set_minimize_threshold(Some(0));
+        assert!(sdd_man.minimize_if_needed());
+        let sdd = sdd_man.get_ext(r);
+        assert_eq!(sdd_man.model_count(sdd), cnt, "count differs for {:?}", cnf);
+    }
+}
+
+/// check that SDDs survive a round trip through the file formats of the SDD
+/// package, and that each literal refers to the vtree leaf of its variable
+#[test]
+pub fn rand_sdd_file() {
+    use rand::Rng;
+    let mut rng = rand::StdRng::new().unwrap();
+    rng.reseed(&[0]);
+    for _ in 1..10 {
+        let num_vars = 8;
+        let cnf = Cnf::rand_cnf(&mut rng, num_vars, 10);
+        let v : Vec<VarLabel> =
+            (0..num_vars).map(|x| VarLabel::new(x as u64)).collect();
+        let t = if rng.gen() { vtree::random(&v, &mut rng) } else { even_split(&v, 2) };
+        let mut man = SddManager::new(t);
+        let f = man.from_cnf(&cnf);
+        let sdd = man.to_sdd_file(f);
+        let vtree = vtree::to_vtree_file(man.get_vtree());
+        let loaded = man.load_sdd_file(&sdd);
+        assert!(man.sdd_eq(f, loaded), "not equal for {:?}:\n{}", cnf, sdd);
+        let (man2, f2) = SddManager::from_sdd_file(&sdd, &vtree);
+        assert_eq!(man2.model_count(f2), man.model_count(f), "count differs for {:?}", cnf);
+        let leaves: HashMap<String, String> = vtree.lines()
+            .filter(|l| l.starts_with("L "))
+            .map(|l| {
+                let tok: Vec<&str> = l.split_whitespace().collect();
+                (String::from(tok[1]), String::from(tok[2]))
+            })
+            .collect();
+        for l in sdd.lines().filter(|l| l.starts_with("L ")) {
+            let tok: Vec<&str> = l.split_whitespace().collect();
+            assert_eq!(leaves[tok[2]], tok[3].trim_start_matches('-'), "bad literal {}", l);
+        }
+    }
+}
+
+/// check that BDDs and SDDs enumerate the same set of models
+#[test]
+pub fn rand_models() {
+    let mut rng = rand::StdRng::new().unwrap();
+    rng.reseed(&[0]);
+    for _ in 1..10 {
+        let num_vars = 8;
+        let cnf = BoolExpr::rand_cnf(&mut rng, num_vars, 6);
+        let v : Vec<VarLabel> =
+            (0..num_vars).map(|x| VarLabel::new(x as u64)).collect();
+        let mut sdd_man = SddManager::new(even_split(&v, 2));
+        let mut bdd_man = BddManager::new_default_order(num_vars);
+        let sdd = sdd_man.from_boolexpr(&cnf);
+        let bdd = bdd_man.from_boolexpr(&cnf);
+        let mut sdd_models: Vec<Vec<bool>> = sdd_man.models(sdd)
+            .map(|m| v.iter().map(|l| m[l]).collect())
+            .collect();
+        let mut bdd_models: Vec<Vec<bool>> = bdd_man.models(bdd)
+            .map(|m| v.iter().map(|l| m[l]).collect())
+            .collect();
+        sdd_models.sort();
+        bdd_models.sort();
+        assert_eq!(sdd_models, bdd_models, "Models differ for {:?}", cnf);
+    }
+}
+
+/// check that the SDD weighted model count agrees with the BDD weighted model
+/// count of the same function
+#[test]
+pub fn rand_sdd_wmc() {
+    let mut rng = rand::StdRng::new().unwrap();
+    rng.reseed(&[0]);
+    for _ in 1..20 {
+        let num_vars = 10;
+        let cnf = BoolExpr::rand_cnf(&mut rng, num_vars, 12);
+        let v : Vec<VarLabel> =
+            (0..num_vars).map(|x| VarLabel::new(x as u64)).collect();
+        let mut weights = HashMap::new();
+        for (i, lbl) in v.iter().enumerate() {
+            weights.insert(*lbl, ((i % 3 + 1) as u64, (i % 4 + 2) as u64));
+        }
+        let mut sdd_man = SddManager::new(even_split(&v, 2));
+        let mut bdd_man = BddManager::new_default_order(num_vars);
+        let sdd = sdd_man.from_boolexpr(&cnf);
+        let bdd = bdd_man.from_boolexpr(&cnf);
+        let sdd_wmc = sdd_man.wmc(sdd, &BddWmc::new_with_default(0, 1, weights.clone()));
+        let bdd_wmc = bdd_man.wmc(bdd, &BddWmc::new_with_default(0, 1, weights));
+        assert_eq!(sdd_wmc, bdd_wmc, "WMC differs for {:?}", cnf);
+    }
+}
+
+/// check that the empirical marginals of BDD and SDD samples match the exact
+/// marginals
+#[test]
+pub fn rand_sample() {
+    let mut rng = rand::StdRng::new().unwrap();
+    rng.reseed(&[0]);
+    for _ in 1..10 {
+        let num_vars = 8;
+        let cnf = BoolExpr::rand_cnf(&mut rng, num_vars, 8);
+        let v : Vec<VarLabel> =
+            (0..num_vars).map(|x| VarLabel::new(x as u64)).collect();
+        let mut weights = HashMap::new();
+        for (i, lbl) in v.iter().enumerate() {
+            let p = (i % 4 + 1) as f64 / 5.0;
+            weights.insert(*lbl, (1.0 - p, p));
+        }
+        let mut sdd_man = SddManager::new(even_split(&v, 2));
+        let mut bdd_man = BddManager::new_default_order(num_vars);
+        let sdd = sdd_man.from_boolexpr(&cnf);
+        let bdd = bdd_man.from_boolexpr(&cnf);
+        let bdd_params = BddWmc::new_with_default(0.0, 1.0, weights.clone());
+        let sdd_params = BddWmc::new_with_default(0.0, 1.0, weights);
+        let z = bdd_man.wmc(bdd, &bdd_params);
+        if z == 0.0 {
+            assert!(bdd_man.sample(bdd, &bdd_params, &mut rng).next().is_none());
+            assert!(sdd_man.sample(sdd, &sdd_params, &mut rng).next().is_none());
+            continue;
+        }
+        let marginals = bdd_man.marginals(bdd, &bdd_params);
+        let num_samples = 4000;
+        let bdd_samples: Vec<HashMap<VarLabel, bool>> =
+            bdd_man.sample(bdd, &bdd_params, &mut rng).take(num_samples).collect();
+        let sdd_samples: Vec<HashMap<VarLabel, bool>> =
+            sdd_man.sample(sdd, &sdd_params, &mut rng).take(num_samples).collect();
+        for m in bdd_samples.iter().chain(sdd_samples.iter()) {
+            assert!(bdd_man.eval_bdd(bdd, m), "sample {:?} falsifies {:?}", m, cnf);
+        }
+        for lbl in v.iter() {
+            let expected = marginals[lbl];
+            for samples in [&bdd_samples, &sdd_samples].iter() {
+                let hits = samples.iter().filter(|m| m[lbl]).count();
+                let freq = hits as f64 / num_samples as f64;
+                assert!((freq - expected).abs() < 0.05,
+                        "marginal of {:?} is {} but sampled {} for {:?}",
+                        lbl, expected, freq, cnf);
+            }
+        }
+    }
+}
+
+/// check that BDDs and SDDs agree on the number of models, and that this is
+/// the number of enumerated models
+#[test]
+pub fn rand_model_count() {
+    let mut rng = rand::StdRng::new().unwrap();
+    rng.reseed(&[0]);
+    for _ in 1..20 {
+        let num_vars = 10;
+        let cnf = BoolExpr::rand_cnf(&mut rng, num_vars, 12);
+        let v : Vec<VarLabel> =
+            (0..num_vars).map(|x| VarLabel::new(x as u64)).collect();
+        let mut sdd_man = SddManager::new(even_split(&v, 2));
+        let mut bdd_man = BddManager::new_default_order(num_vars);
+        let sdd = sdd_man.from_boolexpr(&cnf);
+        let bdd = bdd_man.from_boolexpr(&cnf);
+        let cnt = bdd_man.model_count(bdd);
+        assert_eq!(sdd_man.model_count(sdd), cnt, "Count differs for {:?}", cnf);
+        assert_eq!(BigUint::from(bdd_man.models(bdd).count()), cnt);
+    }
+}
+
+/// check that SDDs and BDDs agree on log-space and max-times weighted model
+/// counts
+#[test]
+pub fn rand_wmc_semiring() {
+    let mut rng = rand::StdRng::new().unwrap();
+    rng.reseed(&[0]);
+    for _ in 1..20 {
+        let num_vars = 10;
+        let cnf = BoolExpr::rand_cnf(&mut rng, num_vars, 12);
+        let v : Vec<VarLabel> =
+            (0..num_vars).map(|x| VarLabel::new(x as u64)).collect();
+        let mut log_w = HashMap::new();
+        let mut max_w = HashMap::new();
+        for (i, lbl) in v.iter().enumerate() {
+            let p = (i as f64 + 1.0) / (num_vars as f64 + 2.0);
+            log_w.insert(*lbl, (LogSemiring((1.0 - p).ln()), LogSemiring(p.ln())));
+            max_w.insert(*lbl, (MaxTimesSemiring(1.0 - p), MaxTimesSemiring(p)));
+        }
+        let mut sdd_man = SddManager::new(even_split(&v, 2));
+        let mut bdd_man = BddManager::new_default_order(num_vars);
+        let sdd = sdd_man.from_boolexpr(&cnf);
+        let bdd = bdd_man.from_boolexpr(&cnf);
+        let sdd_log = sdd_man.wmc_semiring(sdd, &log_w).0;
+        let bdd_log = bdd_man.wmc_semiring(bdd, &log_w).0;
+        assert!((sdd_log.exp() - bdd_log.exp()).abs() < 1e-9,
+                "Log WMC differs for {:?}: {} vs {}", cnf, sdd_log, bdd_log);
+        let sdd_max = sdd_man.wmc_semiring(sdd, &max_w).0;
+        let bdd_max = bdd_man.wmc_semiring(bdd, &max_w).0;
+        assert!((sdd_max - bdd_max).abs() < 1e-12,
+                "Max-times WMC differs for {:?}: {} vs {}", cnf, sdd_max, bdd_max);
+    }
+}
+
+/// check that each marginal agrees with conditioning followed by a weighted
+/// model count, normalized by the count of the whole formula
+#[test]
+pub fn rand_marginals() {
+    let mut rng = rand::StdRng::new().unwrap();
+    rng.reseed(&[0]);
+    for _ in 1..20 {
+        let num_vars = 10;
+        let cnf = BoolExpr::rand_cnf(&mut rng, num_vars, 12);
+        let mut weights = HashMap::new();
+        for i in 0..num_vars {
+            let p = (i % 4 + 1) as f64 / 5.0;
+            weights.insert(VarLabel::new(i as u64), (1.0 - p, p));
+        }
+        let params = BddWmc::new_with_default(0.0, 1.0, weights.clone());
+        let mut man = BddManager::new_default_order(num_vars);
+        let bdd = man.from_boolexpr(&cnf);
+        let z = man.wmc(bdd, &params);
+        if z == 0.0 {
+            continue;
+        }
+        let marg = man.marginals(bdd, &params);
+        for i in 0..num_vars {
+            let lbl = VarLabel::new(i as u64);
+            let cond = man.condition(bdd, lbl, true);
+            let expected = weights[&lbl].1 * man.wmc(cond, &params) / z;
+            assert!((marg[&lbl] - expected).abs() < 1e-9,
+                    "Marginal of {:?} is {} but expected {} for {:?}",
+                    lbl, marg[&lbl], expected, cnf);
+        }
+    }
+}
+
+/// check the gradient of the weighted model count against finite differences,
+/// which are exact since the count is linear in each weight
+#[test]
+pub fn rand_wmc_grad() {
+    let mut rng = rand::StdRng::new().unwrap();
+    rng.reseed(&[0]);
+    for _ in 1..10 {
+        let num_vars = 8;
+        let cnf = BoolExpr::rand_cnf(&mut rng, num_vars, 10);
+        let mut weights = HashMap::new();
+        for i in 0..num_vars {
+            weights.insert(VarLabel::new(i as u64), ((i % 3 + 1) as i64, (i % 4 + 2) as i64));
+        }
+        let mut man = BddManager::new_default_order(num_vars);
+        let bdd = man.from_boolexpr(&cnf);
+        let params = BddWmc::new_with_default(0, 1, weights.clone());
+        let (v, grad) = man.wmc_grad(bdd, &params);
+        assert_eq!(v, man.wmc(bdd, &params));
+        for i in 0..num_vars {
+            let lbl = VarLabel::new(i as u64);
+            let (l, h) = weights[&lbl];
+            let mut low_w = weights.clone();
+            low_w.insert(lbl, (l + 1, h));
+            let mut high_w = weights.clone();
+            high_w.insert(lbl, (l, h + 1));
+            let d_low = man.wmc(bdd, &BddWmc::new_with_default(0, 1, low_w)) - v;
+            let d_high = man.wmc(bdd, &BddWmc::new_with_default(0, 1, high_w)) - v;
+            assert_eq!(grad[&lbl], (d_low, d_high), "Gradient of {:?} differs for {:?}", lbl, cnf);
+        }
+    }
+}
+
+/// check the most probable explanation against a brute-force search over the
+/// enumerated models
+#[test]
+pub fn rand_mpe() {
+    let mut rng = rand::StdRng::new().unwrap();
+    rng.reseed(&[0]);
+    for _ in 1..20 {
+        let num_vars = 8;
+        let cnf = BoolExpr::rand_cnf(&mut rng, num_vars, 10);
+        let mut weights = HashMap::new();
+        for i in 0..num_vars {
+            weights.insert(VarLabel::new(i as u64), ((i % 3 + 1) as u64, (i % 4 + 2) as u64));
+        }
+        let params = BddWmc::new_with_default(0, 1, weights.clone());
+        let mut man = BddManager::new_default_order(num_vars);
+        let bdd = man.from_boolexpr(&cnf);
+        let weight = |m: &HashMap<VarLabel, bool>| {
+            m.iter().fold(1, |acc, (lbl, v)| {
+                let (l, h) = weights[lbl];
+                acc * if *v { h } else { l }
+            })
+        };
+        let best = man.models(bdd).map(|m| weight(&m)).max();
+        match man.mpe(bdd, &params) {
+            None => assert_eq!(best, None),
+            Some((assgn, v)) => {
+                assert_eq!(Some(v), best, "MPE differs for {:?}", cnf);
+                assert!(man.eval_bdd(bdd, &assgn));
+                assert_eq!(weight(&assgn), v);
+            }
+        }
+    }
+}